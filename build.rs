@@ -0,0 +1,4 @@
+// the migrations are embedded by `sqlx::migrate!`, which cannot tell cargo to watch them
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
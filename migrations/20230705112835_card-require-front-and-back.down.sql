@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS card;
+
+CREATE TABLE card (
+    id INTEGER PRIMARY KEY,
+    front TEXT,
+    back TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,2 @@
+DROP TABLE card_deck;
+DROP TABLE deck;
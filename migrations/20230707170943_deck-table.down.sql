@@ -0,0 +1,9 @@
+DROP TABLE IF EXISTS deck;
+
+CREATE TABLE deck (
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL,
+  description TEXT,
+  created_at DATETIME NOT NULL default CURRENT_TIMESTAMP,
+  updated_at DATETIME NOT NULL default CURRENT_TIMESTAMP
+);
@@ -0,0 +1,2 @@
+DROP TABLE card_tag;
+DROP TABLE tag;
@@ -0,0 +1,9 @@
+-- A card schedule stores the SM-2 spaced repetition state of a card
+CREATE TABLE card_schedule (
+    card_id INTEGER PRIMARY KEY,
+    ease_factor REAL NOT NULL DEFAULT 2.5,
+    interval_days INTEGER NOT NULL DEFAULT 0,
+    repetitions INTEGER NOT NULL DEFAULT 0,
+    due_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE
+);
@@ -0,0 +1,5 @@
+ALTER TABLE card_schedule DROP COLUMN last_reviewed_at;
+ALTER TABLE card_schedule DROP COLUMN difficulty;
+ALTER TABLE card_schedule DROP COLUMN stability;
+
+ALTER TABLE deck DROP COLUMN scheduler;
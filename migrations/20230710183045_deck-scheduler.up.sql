@@ -0,0 +1,7 @@
+-- A deck chooses the algorithm that schedules its cards
+ALTER TABLE deck ADD COLUMN scheduler TEXT NOT NULL DEFAULT 'sm2';
+
+-- FSRS keeps a memory state per card and needs the time since the last review
+ALTER TABLE card_schedule ADD COLUMN stability REAL;
+ALTER TABLE card_schedule ADD COLUMN difficulty REAL;
+ALTER TABLE card_schedule ADD COLUMN last_reviewed_at DATETIME;
@@ -0,0 +1 @@
+DROP TABLE fsrs_params;
@@ -0,0 +1,12 @@
+-- Personalised FSRS weights fitted from the answer history of a user, a deck, or both
+CREATE TABLE fsrs_params (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    deck_id INTEGER,
+    weights TEXT NOT NULL,
+    log_loss REAL NOT NULL,
+    review_count INTEGER NOT NULL,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+ALTER TABLE answer DROP COLUMN grade;
+
+ALTER TABLE deck DROP COLUMN review_mode;
@@ -0,0 +1,5 @@
+-- A deck is reviewed either by typing the back or by flipping the card and self-grading
+ALTER TABLE deck ADD COLUMN review_mode TEXT NOT NULL DEFAULT 'typed';
+
+-- The self-assigned grade of an answer, from 1 (again) to 4 (easy)
+ALTER TABLE answer ADD COLUMN grade INTEGER;
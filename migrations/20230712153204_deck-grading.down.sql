@@ -0,0 +1 @@
+ALTER TABLE deck DROP COLUMN grading;
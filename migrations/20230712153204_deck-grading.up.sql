@@ -0,0 +1,2 @@
+-- A deck chooses how typed answers are compared with the back of a card
+ALTER TABLE deck ADD COLUMN grading TEXT NOT NULL DEFAULT 'exact';
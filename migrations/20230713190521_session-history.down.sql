@@ -0,0 +1,30 @@
+-- Answers of guests and the time answers took have nowhere to go in the old table
+CREATE TABLE answer_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER NOT NULL,
+    session_id INTEGER NOT NULL,
+    answer TEXT NOT NULL,
+    correct_answer TEXT NOT NULL,
+    time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    grade INTEGER,
+    FOREIGN KEY (user_id) REFERENCES user (id),
+    FOREIGN KEY (card_id) REFERENCES card (id),
+    FOREIGN KEY (deck_id) REFERENCES deck (id),
+    FOREIGN KEY (session_id) REFERENCES session (id)
+);
+
+INSERT INTO answer_old (
+    id, user_id, card_id, deck_id, session_id, answer, correct_answer, time, grade
+)
+SELECT id, user_id, card_id, deck_id, session_id, answer, correct_answer, time, grade
+FROM answer
+WHERE user_id IS NOT NULL;
+
+DROP TABLE answer;
+
+ALTER TABLE answer_old RENAME TO answer;
+
+ALTER TABLE session DROP COLUMN deck_id;
+ALTER TABLE session DROP COLUMN user_id;
@@ -0,0 +1,32 @@
+-- A session is started by a user reviewing a deck
+ALTER TABLE session ADD COLUMN user_id INTEGER REFERENCES user (id) ON DELETE CASCADE;
+ALTER TABLE session ADD COLUMN deck_id INTEGER REFERENCES deck (id) ON DELETE CASCADE;
+
+-- Rebuild the answer table so that guests (without a user) can answer too,
+-- and so that every attempt records how long it took
+CREATE TABLE answer_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER NOT NULL,
+    session_id INTEGER NOT NULL,
+    answer TEXT NOT NULL,
+    correct_answer TEXT NOT NULL,
+    grade INTEGER,
+    elapsed_ms INTEGER,
+    time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE,
+    FOREIGN KEY (session_id) REFERENCES session (id) ON DELETE CASCADE
+);
+
+INSERT INTO answer_new (
+    id, user_id, card_id, deck_id, session_id, answer, correct_answer, grade, time
+)
+SELECT id, user_id, card_id, deck_id, session_id, answer, correct_answer, grade, time
+FROM answer;
+
+DROP TABLE answer;
+
+ALTER TABLE answer_new RENAME TO answer;
@@ -0,0 +1,28 @@
+ALTER TABLE deck DROP COLUMN owner_id;
+
+-- Only the progress without a user fits in a schedule keyed by card
+CREATE TABLE card_schedule_old (
+    card_id INTEGER PRIMARY KEY,
+    ease_factor REAL NOT NULL DEFAULT 2.5,
+    interval_days INTEGER NOT NULL DEFAULT 0,
+    repetitions INTEGER NOT NULL DEFAULT 0,
+    due_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    stability REAL,
+    difficulty REAL,
+    last_reviewed_at DATETIME,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE
+);
+
+INSERT INTO card_schedule_old (
+    card_id, ease_factor, interval_days, repetitions, due_at, stability, difficulty,
+    last_reviewed_at
+)
+SELECT
+    card_id, ease_factor, interval_days, repetitions, due_at, stability, difficulty,
+    last_reviewed_at
+FROM card_schedule
+WHERE user_id IS NULL;
+
+DROP TABLE card_schedule;
+
+ALTER TABLE card_schedule_old RENAME TO card_schedule;
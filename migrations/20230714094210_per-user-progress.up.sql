@@ -0,0 +1,34 @@
+-- Rebuild the card schedule so that every user has their own progress on a card.
+-- Guests share the schedule without a user, which is where the existing progress goes.
+CREATE TABLE card_schedule_new (
+    user_id INTEGER,
+    card_id INTEGER NOT NULL,
+    ease_factor REAL NOT NULL DEFAULT 2.5,
+    interval_days INTEGER NOT NULL DEFAULT 0,
+    repetitions INTEGER NOT NULL DEFAULT 0,
+    due_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    stability REAL,
+    difficulty REAL,
+    last_reviewed_at DATETIME,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE
+);
+
+INSERT INTO card_schedule_new (
+    card_id, ease_factor, interval_days, repetitions, due_at, stability, difficulty,
+    last_reviewed_at
+)
+SELECT
+    card_id, ease_factor, interval_days, repetitions, due_at, stability, difficulty,
+    last_reviewed_at
+FROM card_schedule;
+
+DROP TABLE card_schedule;
+
+ALTER TABLE card_schedule_new RENAME TO card_schedule;
+
+-- NULL is never equal to NULL, so guests are keyed as user 0
+CREATE UNIQUE INDEX card_schedule_user_card ON card_schedule (IFNULL(user_id, 0), card_id);
+
+-- A deck without an owner is shared with everyone, otherwise only its owner sees it
+ALTER TABLE deck ADD COLUMN owner_id INTEGER REFERENCES user (id) ON DELETE CASCADE;
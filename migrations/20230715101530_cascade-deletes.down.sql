@@ -0,0 +1,73 @@
+-- Answers and sessions whose deck was deleted have nowhere to go in the old tables
+ALTER TABLE session RENAME TO session_old;
+
+CREATE TABLE session (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    end_time DATETIME,
+    user_id INTEGER REFERENCES user (id) ON DELETE CASCADE,
+    deck_id INTEGER REFERENCES deck (id) ON DELETE CASCADE
+);
+
+INSERT INTO session (id, start_time, end_time, user_id, deck_id)
+SELECT id, start_time, end_time, user_id, deck_id FROM session_old
+WHERE deck_id IS NOT NULL;
+
+CREATE TABLE answer_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER NOT NULL,
+    session_id INTEGER NOT NULL,
+    answer TEXT NOT NULL,
+    correct_answer TEXT NOT NULL,
+    grade INTEGER,
+    elapsed_ms INTEGER,
+    time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE,
+    FOREIGN KEY (session_id) REFERENCES session (id) ON DELETE CASCADE
+);
+
+INSERT INTO answer_old (
+    id, user_id, card_id, deck_id, session_id, answer, correct_answer, grade, elapsed_ms, time
+)
+SELECT id, user_id, card_id, deck_id, session_id, answer, correct_answer, grade, elapsed_ms, time
+FROM answer
+WHERE deck_id IS NOT NULL AND session_id IN (SELECT id FROM session);
+
+DROP TABLE answer;
+
+ALTER TABLE answer_old RENAME TO answer;
+
+DROP TABLE session_old;
+
+CREATE TABLE card_deck_old (
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER NOT NULL,
+    PRIMARY KEY (card_id, deck_id),
+    FOREIGN KEY (card_id) REFERENCES card(id),
+    FOREIGN KEY (deck_id) REFERENCES deck(id)
+);
+
+INSERT INTO card_deck_old (card_id, deck_id) SELECT card_id, deck_id FROM card_deck;
+
+DROP TABLE card_deck;
+
+ALTER TABLE card_deck_old RENAME TO card_deck;
+
+CREATE TABLE card_tag_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    card_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    FOREIGN KEY (card_id) REFERENCES card (id),
+    FOREIGN KEY (tag_id) REFERENCES tag (id),
+    UNIQUE (card_id, tag_id)
+);
+
+INSERT INTO card_tag_old (id, card_id, tag_id) SELECT id, card_id, tag_id FROM card_tag;
+
+DROP TABLE card_tag;
+
+ALTER TABLE card_tag_old RENAME TO card_tag;
@@ -0,0 +1,84 @@
+-- Rows left behind while foreign keys were not enforced point at nothing
+DELETE FROM card_deck WHERE card_id NOT IN (SELECT id FROM card) OR deck_id NOT IN (SELECT id FROM deck);
+DELETE FROM card_tag WHERE card_id NOT IN (SELECT id FROM card) OR tag_id NOT IN (SELECT id FROM tag);
+DELETE FROM card_schedule WHERE card_id NOT IN (SELECT id FROM card);
+DELETE FROM answer WHERE card_id NOT IN (SELECT id FROM card);
+
+-- Rebuild the link tables so that a link goes away with its card, deck or tag
+CREATE TABLE card_deck_new (
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER NOT NULL,
+    PRIMARY KEY (card_id, deck_id),
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE
+);
+
+INSERT INTO card_deck_new (card_id, deck_id) SELECT card_id, deck_id FROM card_deck;
+
+DROP TABLE card_deck;
+
+ALTER TABLE card_deck_new RENAME TO card_deck;
+
+CREATE TABLE card_tag_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    card_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tag (id) ON DELETE CASCADE,
+    UNIQUE (card_id, tag_id)
+);
+
+INSERT INTO card_tag_new (id, card_id, tag_id) SELECT id, card_id, tag_id FROM card_tag;
+
+DROP TABLE card_tag;
+
+ALTER TABLE card_tag_new RENAME TO card_tag;
+
+-- Answers are the review history the FSRS weights are fitted on, so they outlive the deck
+-- they were given in rather than going with it.
+-- Renaming session first points the answers at session_old, so that neither table is
+-- dropped while rows of the other refer to it.
+ALTER TABLE session RENAME TO session_old;
+
+CREATE TABLE session (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    start_time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    end_time DATETIME,
+    user_id INTEGER REFERENCES user (id) ON DELETE CASCADE,
+    deck_id INTEGER REFERENCES deck (id) ON DELETE SET NULL
+);
+
+INSERT INTO session (id, start_time, end_time, user_id, deck_id)
+SELECT id, start_time, end_time, user_id, (SELECT id FROM deck WHERE id = session_old.deck_id)
+FROM session_old;
+
+CREATE TABLE answer_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER,
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER,
+    session_id INTEGER NOT NULL,
+    answer TEXT NOT NULL,
+    correct_answer TEXT NOT NULL,
+    grade INTEGER,
+    elapsed_ms INTEGER,
+    time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE SET NULL,
+    FOREIGN KEY (session_id) REFERENCES session (id) ON DELETE CASCADE
+);
+
+INSERT INTO answer_new (
+    id, user_id, card_id, deck_id, session_id, answer, correct_answer, grade, elapsed_ms, time
+)
+SELECT
+    id, user_id, card_id, (SELECT id FROM deck WHERE id = answer.deck_id), session_id, answer,
+    correct_answer, grade, elapsed_ms, time
+FROM answer;
+
+DROP TABLE answer;
+
+ALTER TABLE answer_new RENAME TO answer;
+
+DROP TABLE session_old;
@@ -0,0 +1,5 @@
+DELETE FROM card WHERE deleted_at IS NOT NULL;
+DELETE FROM deck WHERE deleted_at IS NOT NULL;
+
+ALTER TABLE card DROP COLUMN deleted_at;
+ALTER TABLE deck DROP COLUMN deleted_at;
@@ -0,0 +1,3 @@
+-- Deleted cards and decks stay in the trash until they are restored or purged
+ALTER TABLE card ADD COLUMN deleted_at DATETIME;
+ALTER TABLE deck ADD COLUMN deleted_at DATETIME;
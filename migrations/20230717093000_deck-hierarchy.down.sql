@@ -0,0 +1 @@
+DROP VIEW deck_tree;
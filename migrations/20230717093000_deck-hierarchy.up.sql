@@ -0,0 +1,8 @@
+-- Decks nest by name, so Languages::Spanish::Verbs is under Languages::Spanish and Languages.
+-- The tree pairs every deck with itself and with each deck under it, trashed or not.
+CREATE VIEW deck_tree AS
+SELECT ancestor.id AS ancestor_id, deck.id AS deck_id
+FROM deck AS ancestor
+JOIN deck
+    ON deck.id = ancestor.id
+    OR substr(deck.name, 1, length(ancestor.name) + 2) = ancestor.name || '::';
@@ -0,0 +1,2 @@
+-- A suspended card is kept out of reviews until it is unsuspended
+ALTER TABLE card ADD COLUMN suspended BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+DROP TRIGGER card_fts_update;
+DROP TRIGGER card_fts_delete;
+DROP TRIGGER card_fts_insert;
+DROP TABLE card_fts;
@@ -0,0 +1,39 @@
+-- A full-text index of the front and back of the cards, which matches words regardless of
+-- case and accents and ranks the matches
+CREATE VIRTUAL TABLE card_fts USING fts5 (
+    front,
+    back,
+    content = 'card',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO card_fts (card_fts) VALUES ('rebuild');
+
+-- The index keeps the old text of a card until it is told to delete it
+CREATE TRIGGER card_fts_insert
+AFTER INSERT
+ON card
+FOR EACH ROW
+BEGIN
+    INSERT INTO card_fts (rowid, front, back) VALUES (NEW.id, NEW.front, NEW.back);
+END;
+
+CREATE TRIGGER card_fts_delete
+AFTER DELETE
+ON card
+FOR EACH ROW
+BEGIN
+    INSERT INTO card_fts (card_fts, rowid, front, back)
+    VALUES ('delete', OLD.id, OLD.front, OLD.back);
+END;
+
+CREATE TRIGGER card_fts_update
+AFTER UPDATE OF front, back
+ON card
+FOR EACH ROW
+BEGIN
+    INSERT INTO card_fts (card_fts, rowid, front, back)
+    VALUES ('delete', OLD.id, OLD.front, OLD.back);
+    INSERT INTO card_fts (rowid, front, back) VALUES (NEW.id, NEW.front, NEW.back);
+END;
@@ -0,0 +1,5 @@
+DROP VIEW deck_card;
+DROP TABLE filtered_card;
+
+ALTER TABLE deck DROP COLUMN search_limit;
+ALTER TABLE deck DROP COLUMN search;
@@ -0,0 +1,18 @@
+-- A filtered deck is made of the cards matching a search instead of cards added to it
+ALTER TABLE deck ADD COLUMN search TEXT;
+ALTER TABLE deck ADD COLUMN search_limit INTEGER;
+
+-- The cards a filtered deck borrows, which stay in their home decks and are only reviewed
+-- through the filtered deck until they are given back. A card is in one filtered deck at most.
+CREATE TABLE filtered_card (
+    card_id INTEGER PRIMARY KEY,
+    deck_id INTEGER NOT NULL,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE
+);
+
+-- The cards of every deck, whether they were added to it or borrowed by it
+CREATE VIEW deck_card AS
+SELECT card_id, deck_id FROM card_deck
+UNION ALL
+SELECT card_id, deck_id FROM filtered_card;
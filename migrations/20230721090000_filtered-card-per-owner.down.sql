@@ -0,0 +1,21 @@
+-- A card goes back to being in one filtered deck at most, the first one that borrowed it
+DROP VIEW deck_card;
+
+CREATE TABLE filtered_card_old (
+    card_id INTEGER PRIMARY KEY,
+    deck_id INTEGER NOT NULL,
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE
+);
+
+INSERT OR IGNORE INTO filtered_card_old (card_id, deck_id)
+SELECT card_id, deck_id FROM filtered_card ORDER BY rowid;
+
+DROP TABLE filtered_card;
+
+ALTER TABLE filtered_card_old RENAME TO filtered_card;
+
+CREATE VIEW deck_card AS
+SELECT card_id, deck_id FROM card_deck
+UNION ALL
+SELECT card_id, deck_id FROM filtered_card;
@@ -0,0 +1,22 @@
+-- A card borrowed by a private filtered deck is only taken out of the reviews of its owner,
+-- so filtered decks of different users can borrow the same card
+DROP VIEW deck_card;
+
+CREATE TABLE filtered_card_new (
+    card_id INTEGER NOT NULL,
+    deck_id INTEGER NOT NULL,
+    PRIMARY KEY (card_id, deck_id),
+    FOREIGN KEY (card_id) REFERENCES card (id) ON DELETE CASCADE,
+    FOREIGN KEY (deck_id) REFERENCES deck (id) ON DELETE CASCADE
+);
+
+INSERT INTO filtered_card_new (card_id, deck_id) SELECT card_id, deck_id FROM filtered_card;
+
+DROP TABLE filtered_card;
+
+ALTER TABLE filtered_card_new RENAME TO filtered_card;
+
+CREATE VIEW deck_card AS
+SELECT card_id, deck_id FROM card_deck
+UNION ALL
+SELECT card_id, deck_id FROM filtered_card;
@@ -0,0 +1,3 @@
+ALTER TABLE session DROP COLUMN review_mode;
+
+DROP TABLE session_card;
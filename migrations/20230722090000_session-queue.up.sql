@@ -0,0 +1,9 @@
+-- The cards a session was started with and the review mode it was asked for, so that
+-- resuming it keeps the limits, tag and mode of the review
+CREATE TABLE session_card (
+    session_id INTEGER NOT NULL REFERENCES session (id) ON DELETE CASCADE,
+    card_id INTEGER NOT NULL REFERENCES card (id) ON DELETE CASCADE,
+    PRIMARY KEY (session_id, card_id)
+);
+
+ALTER TABLE session ADD COLUMN review_mode TEXT;
@@ -0,0 +1,15 @@
+-- The cards that went to the trash with a deck, by the deck they were in, so that restoring
+-- the deck brings back these cards and no others
+CREATE TABLE trashed_card (
+    card_id INTEGER NOT NULL REFERENCES card (id) ON DELETE CASCADE,
+    deck_id INTEGER NOT NULL REFERENCES deck (id) ON DELETE CASCADE,
+    PRIMARY KEY (card_id, deck_id)
+);
+
+-- the cards in the trash were matched to their decks by the time they were deleted
+INSERT INTO trashed_card (card_id, deck_id)
+SELECT card_deck.card_id, card_deck.deck_id
+FROM card_deck
+JOIN card ON card.id = card_deck.card_id
+JOIN deck ON deck.id = card_deck.deck_id
+WHERE card.deleted_at = deck.deleted_at;
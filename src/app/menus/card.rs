@@ -1,9 +1,18 @@
 use super::traits::{MenuOptions, ProcessOption};
 use super::MenuState;
-use super::utils::{prompt_for_card_details, prompt_for_card_id};
+use super::utils::{
+    prompt_for_card_details, prompt_for_card_id, prompt_for_confirmation, prompt_for_search,
+    prompt_for_tag,
+};
 
 use crate::app::state::AppState;
-use crate::queries::{create_card, delete_card, list_cards, update_card};
+use crate::hierarchy::normalize_name;
+use crate::output::print_rows;
+use crate::queries::{
+    create_card, create_tag, delete_card, list_cards, list_cards_for_tag, query_card_exists,
+    query_tag_id, search_cards, tag_card, untag_card, update_card,
+};
+use crate::search::Search;
 use async_trait::async_trait;
 use sqlx::{Sqlite, Transaction};
 
@@ -15,6 +24,10 @@ pub enum CardMenuOptions {
     List,
     Update,
     Delete,
+    AddTag,
+    RemoveTag,
+    ListByTag,
+    Search,
     GoToMainMenu,
     GoToSubMenu,
     GoBack(AppState),
@@ -36,34 +49,100 @@ impl ProcessOption for CardMenuOptions {
     async fn process(
         self,
         tx: &mut Transaction<'_, Sqlite>,
-        _state: &AppState,
+        state: &AppState,
     ) -> Result<(MenuState, bool), sqlx::Error> {
         match self {
             CardMenuOptions::Create => {
                 println!("Creating a card");
                 let (front, back) = prompt_for_card_details()?;
 
-                create_card(
+                let id = create_card(
                     tx,
                     front.unwrap_or("".to_string()),
                     back.unwrap_or("".to_string()),
                 )
                 .await?;
+                println!("Created card with id: {}", id);
             }
             CardMenuOptions::List => {
                 println!("Listing all cards");
-                list_cards(tx).await?;
+                print_rows(&list_cards(tx).await?, state.format());
             }
             CardMenuOptions::Update => {
                 println!("Updating a card");
                 let id = prompt_for_card_id()?;
                 let (front, back) = prompt_for_card_details()?;
-                update_card(tx, id, front, back).await?;
+                if !update_card(tx, id, front, back).await? {
+                    println!("No card with id: {} found", id);
+                }
             }
             CardMenuOptions::Delete => {
                 println!("Deleting a card");
                 let id = prompt_for_card_id()?;
-                delete_card(tx, id).await?;
+                let question = format!("Move card with id {} to the trash?", id);
+                if !prompt_for_confirmation(&question)? {
+                    println!("Kept card with id: {}", id);
+                } else if delete_card(tx, id).await? {
+                    println!("Moved card with id {} to the trash", id);
+                } else {
+                    println!("No card with id: {} found", id);
+                }
+            }
+            CardMenuOptions::AddTag => {
+                println!("Tagging a card");
+                let id = prompt_for_card_id()?;
+                let tag = prompt_for_tag()?;
+                if !query_card_exists(tx, id).await? {
+                    println!("No card with id: {} found", id);
+                } else {
+                    // tags that do not exist yet are created on the spot
+                    let tag_id = match query_tag_id(tx, &tag).await? {
+                        Some(tag_id) => Some(tag_id),
+                        None => match normalize_name(&tag) {
+                            Some(name) => Some(create_tag(tx, name, None).await?),
+                            None => None,
+                        },
+                    };
+                    match tag_id {
+                        Some(tag_id) => {
+                            tag_card(tx, id, tag_id).await?;
+                            println!("Tagged card with id {} with tag with id {}", id, tag_id);
+                        }
+                        None => println!("The name of a tag cannot be empty or have empty levels"),
+                    }
+                }
+            }
+            CardMenuOptions::RemoveTag => {
+                println!("Untagging a card");
+                let id = prompt_for_card_id()?;
+                let tag = prompt_for_tag()?;
+                match query_tag_id(tx, &tag).await? {
+                    Some(tag_id) if untag_card(tx, id, tag_id).await? => {
+                        println!("Took tag with id {} off card with id {}", tag_id, id)
+                    }
+                    Some(_) => println!("Card with id: {} does not have tag {}", id, tag),
+                    None => println!("No tag with id or name: {} found", tag),
+                }
+            }
+            CardMenuOptions::ListByTag => {
+                println!("Listing the cards with a tag");
+                let tag = prompt_for_tag()?;
+                match query_tag_id(tx, &tag).await? {
+                    Some(tag_id) => {
+                        print_rows(&list_cards_for_tag(tx, tag_id).await?, state.format())
+                    }
+                    None => println!("No tag with id or name: {} found", tag),
+                }
+            }
+            CardMenuOptions::Search => {
+                println!("Searching the cards");
+                match prompt_for_search()?.parse::<Search>() {
+                    Ok(search) => print_rows(
+                        &search_cards(tx, state.user().id(), &search).await?,
+                        state.format(),
+                    ),
+                    Err(e) => println!("Invalid search: {}", e),
+                }
             }
             CardMenuOptions::GoToMainMenu => {
                 println!("Going to main menu");
@@ -100,13 +179,17 @@ impl ProcessOption for CardSubMenuOptions {
                 println!("Updating the front of a card");
                 let id = prompt_for_card_id()?;
                 let front = "test".to_string();
-                update_card(tx, id, Some(front), None).await?;
+                if !update_card(tx, id, Some(front), None).await? {
+                    println!("No card with id: {} found", id);
+                }
             }
             CardSubMenuOptions::Back => {
                 println!("Updating the back of a card");
                 let id = prompt_for_card_id()?;
                 let back = "test".to_string();
-                update_card(tx, id, None, Some(back)).await?;
+                if !update_card(tx, id, None, Some(back)).await? {
+                    println!("No card with id: {} found", id);
+                }
             }
             CardSubMenuOptions::GoToCardMenu => {
                 println!("Going to card menu");
@@ -1,13 +1,26 @@
 use super::traits::{MenuOptions, ProcessOption};
-use super::utils::{parse_input, prompt_for_card_id, prompt_for_deck_details, prompt_for_deck_id};
+use super::utils::{
+    parse_input, prompt_for_card_id, prompt_for_confirmation, prompt_for_deck_cards_deletion,
+    prompt_for_deck_details, prompt_for_deck_id, prompt_for_grading, prompt_for_private_deck,
+    prompt_for_search, prompt_for_search_limit, prompt_for_tag,
+};
 use super::MenuState;
 
 use crate::app::menus::utils::prompt_for_card_details;
 use crate::app::state::AppState;
+use crate::cli::is_unique_violation;
+use crate::grading::Grader;
+use crate::hierarchy::normalize_name;
+use crate::output::print_rows;
 use crate::queries::{
-    add_card_to_deck, create_card, create_deck, delete_deck, list_cards, list_cards_for_deck,
-    list_decks, query_deck_exists, query_deck_info, review_deck, update_deck,
+    add_card_to_deck, create_card, create_deck, create_filtered_deck, delete_deck,
+    empty_filtered_deck, list_cards, list_cards_for_deck, list_decks, query_deck_info,
+    query_deck_search, query_deck_stats, query_deck_visible, query_tag_id, rebuild_filtered_deck,
+    set_deck_grader, set_deck_review_mode, set_deck_scheduler, update_deck, DeckError,
 };
+use crate::review::ReviewMode;
+use crate::scheduler::SchedulerKind;
+use crate::search::Search;
 use async_trait::async_trait;
 use sqlx::{Sqlite, Transaction};
 use std::io::{self, Write};
@@ -21,6 +34,7 @@ pub enum DeckMenuOptions {
     Update,
     Delete,
     ChooseDeck,
+    CreateFiltered,
 }
 
 #[derive(EnumIter, Display, Debug, PartialEq)]
@@ -31,11 +45,19 @@ pub enum DeckDetailMenuOptions {
     AddCard(i64),
     CreateCard(i64),
     Review(i64),
+    ReviewTag(i64),
+    SetScheduler(i64),
+    SetReviewMode(i64),
+    SetGrading(i64),
+    RebuildFiltered(i64),
+    EmptyFiltered(i64),
     GoBack(AppState),
     Quit,
 }
 
 impl MenuOptions for DeckMenuOptions {}
+impl MenuOptions for SchedulerKind {}
+impl MenuOptions for ReviewMode {}
 impl MenuOptions for DeckDetailMenuOptions {
     fn from_input() -> Option<Self> {
         let mut input = String::new();
@@ -52,37 +74,85 @@ impl ProcessOption for DeckMenuOptions {
     async fn process(
         self,
         tx: &mut Transaction<'_, Sqlite>,
-        _state: &AppState,
+        state: &AppState,
     ) -> Result<(MenuState, bool), sqlx::Error> {
+        let user_id = state.user().id();
         match self {
             DeckMenuOptions::Create => {
                 println!("Creating a deck");
                 let (name, description) = prompt_for_deck_details()?;
-                create_deck(tx, name, description).await?;
+                let Some(name) = normalize_name(&name) else {
+                    println!("The name of a deck cannot be empty or have empty levels");
+                    return Ok((MenuState::DeckMenu, true));
+                };
+                // guests have no account to keep a private deck in
+                let owner_id = match user_id {
+                    Some(_) if prompt_for_private_deck()? => user_id,
+                    _ => None,
+                };
+                match create_deck(tx, name.clone(), description, owner_id).await {
+                    Ok(id) => println!("Created deck with id: {}", id),
+                    Err(DeckError::Database(e)) if is_unique_violation(&e) => {
+                        println!("A deck named {} already exists", name)
+                    }
+                    Err(DeckError::Database(e)) => return Err(e),
+                    Err(e) => println!("{}", e),
+                }
             }
             DeckMenuOptions::Update => {
                 println!("Updating a deck... insert an id");
                 let id = prompt_for_deck_id()?;
                 println!("Updating deck with id {}", id);
                 let (name, description) = prompt_for_deck_details()?;
-                update_deck(tx, id, name, description).await?;
+                // a blank name keeps the current one
+                let name = match name.as_str() {
+                    "" => None,
+                    name => match normalize_name(name) {
+                        Some(name) => Some(name),
+                        None => {
+                            println!("The name of a deck cannot have empty levels");
+                            return Ok((MenuState::DeckMenu, true));
+                        }
+                    },
+                };
+                // a blank description clears it
+                let description = Some(description.filter(|description| !description.is_empty()));
+                match update_deck(tx, user_id, id, name.clone(), description).await {
+                    Ok(true) => {}
+                    Ok(false) => println!("No deck with id: {} found", id),
+                    Err(DeckError::Database(e)) if is_unique_violation(&e) => {
+                        println!("A deck named {} already exists", name.unwrap_or_default())
+                    }
+                    Err(DeckError::Database(e)) => return Err(e),
+                    Err(e) => println!("{}", e),
+                }
             }
             DeckMenuOptions::Delete => {
                 println!("Deleting a deck... insert an id");
                 let id = prompt_for_deck_id()?;
                 println!("Deleting deck with id {}", id);
-                delete_deck(tx, id).await?;
+                let question = format!("Move deck with id {} and its subdecks to the trash?", id);
+                if !prompt_for_confirmation(&question)? {
+                    println!("Kept deck with id: {}", id);
+                    return Ok((MenuState::DeckMenu, true));
+                }
+                let delete_cards = prompt_for_deck_cards_deletion()?;
+                if delete_deck(tx, user_id, id, delete_cards).await? {
+                    println!("Moved deck with id {} to the trash", id);
+                } else {
+                    println!("No deck with id: {} found", id);
+                }
             }
             DeckMenuOptions::List => {
                 println!("Listing all decks");
-                list_decks(tx).await?;
+                print_rows(&list_decks(tx, user_id).await?, state.format());
             }
             DeckMenuOptions::ChooseDeck => {
                 println!("Choosing a deck");
                 let id = prompt_for_deck_id()?;
                 println!("Chose deck with id {}", id);
-                // check if deck exists first
-                let does_exist: bool = query_deck_exists(tx, id).await?;
+                // check if deck exists first, other users' private decks are hidden
+                let does_exist: bool = query_deck_visible(tx, user_id, id).await?;
                 if !does_exist {
                     println!("Deck does not exist");
                 } else {
@@ -92,6 +162,44 @@ impl ProcessOption for DeckMenuOptions {
 
                 return Ok((MenuState::DeckMenu, true));
             }
+            DeckMenuOptions::CreateFiltered => {
+                println!("Creating a filtered deck that borrows the cards matching a search");
+                let (name, description) = prompt_for_deck_details()?;
+                let Some(name) = normalize_name(&name) else {
+                    println!("The name of a deck cannot be empty or have empty levels");
+                    return Ok((MenuState::DeckMenu, true));
+                };
+                let search = prompt_for_search()?;
+                if let Err(e) = search.parse::<Search>() {
+                    println!("Invalid search: {}", e);
+                    return Ok((MenuState::DeckMenu, true));
+                }
+                let limit = prompt_for_search_limit()?;
+                let owner_id = match user_id {
+                    Some(_) if prompt_for_private_deck()? => user_id,
+                    _ => None,
+                };
+                let res =
+                    create_filtered_deck(tx, name.clone(), description, search, limit, owner_id)
+                        .await;
+                let id = match res {
+                    Ok(id) => id,
+                    Err(DeckError::Database(e)) if is_unique_violation(&e) => {
+                        println!("A deck named {} already exists", name);
+                        return Ok((MenuState::DeckMenu, true));
+                    }
+                    Err(DeckError::Database(e)) => return Err(e),
+                    Err(e) => {
+                        println!("{}", e);
+                        return Ok((MenuState::DeckMenu, true));
+                    }
+                };
+                let borrowed = rebuild_filtered_deck(tx, id).await?;
+                println!(
+                    "Created filtered deck with id {} and {} cards",
+                    id, borrowed
+                );
+            }
         }
         Ok((MenuState::DeckMenu, true))
     }
@@ -102,7 +210,7 @@ impl ProcessOption for DeckDetailMenuOptions {
     async fn process(
         self,
         tx: &mut Transaction<'_, Sqlite>,
-        _state: &AppState,
+        state: &AppState,
     ) -> Result<(MenuState, bool), sqlx::Error> {
         println!("Making DeckDetailMenuOptions decision for {:?}", self);
         match self {
@@ -110,26 +218,48 @@ impl ProcessOption for DeckDetailMenuOptions {
                 println!("Viewing a deck with id {}", id);
                 let deck_info: String = query_deck_info(tx, id).await;
                 println!("Deck info: {deck_info}");
+                let stats = query_deck_stats(tx, state.user().id(), id).await?;
+                println!(
+                    "{} cards, {} new, {} due, {} reviews",
+                    stats.cards, stats.new, stats.due, stats.reviews
+                );
+                if let Some(accuracy) = stats.accuracy() {
+                    println!("{:.0}% of your answers were correct", accuracy * 100.0);
+                }
                 return Ok((MenuState::DeckDetailMenu(id), true));
             }
             DeckDetailMenuOptions::ListAllCards(id) => {
                 println!("Listing all cards");
-                list_cards(tx).await?;
+                print_rows(&list_cards(tx).await?, state.format());
                 return Ok((MenuState::DeckDetailMenu(id), true));
             }
             DeckDetailMenuOptions::ListCards(id) => {
                 println!("Listing cards for deck with id {}", id);
-                list_cards_for_deck(tx, id).await?;
+                print_rows(&list_cards_for_deck(tx, id).await?, state.format());
                 return Ok((MenuState::DeckDetailMenu(id), true));
             }
             DeckDetailMenuOptions::AddCard(id) => {
                 println!("Adding a card to deck with id {}", id);
+                if query_deck_search(tx, id).await?.is_some() {
+                    println!("Filtered decks borrow their cards, rebuild it instead");
+                    return Ok((MenuState::DeckDetailMenu(id), true));
+                }
                 let card_id = prompt_for_card_id()?;
-                add_card_to_deck(tx, card_id, id).await?;
+                match add_card_to_deck(tx, card_id, id).await {
+                    Ok(()) => println!("Added card with id {} to deck with id {}", card_id, id),
+                    Err(e) if is_unique_violation(&e) => {
+                        println!("Card with id {} is already in deck with id {}", card_id, id)
+                    }
+                    Err(e) => return Err(e),
+                }
                 return Ok((MenuState::DeckDetailMenu(id), true));
             }
             DeckDetailMenuOptions::CreateCard(id) => {
                 println!("Creating a card for deck with id {}", id);
+                if query_deck_search(tx, id).await?.is_some() {
+                    println!("Filtered decks borrow their cards, create it in a home deck");
+                    return Ok((MenuState::DeckDetailMenu(id), true));
+                }
                 let (front, back) = prompt_for_card_details()?;
                 let card_id = create_card(
                     tx,
@@ -142,7 +272,72 @@ impl ProcessOption for DeckDetailMenuOptions {
             }
             DeckDetailMenuOptions::Review(id) => {
                 println!("Reviewing a deck with id {}", id);
-                review_deck(tx, id).await?;
+                // reviews commit their answers as they go, outside of the menu transaction
+                return Ok((MenuState::Review(id, None), true));
+            }
+            DeckDetailMenuOptions::ReviewTag(id) => {
+                println!("Reviewing the cards of deck with id {} that have a tag", id);
+                let tag = prompt_for_tag()?;
+                let Some(tag_id) = query_tag_id(tx, &tag).await? else {
+                    println!("No tag with id or name: {} found", tag);
+                    return Ok((MenuState::DeckDetailMenu(id), true));
+                };
+                return Ok((MenuState::Review(id, Some(tag_id)), true));
+            }
+            DeckDetailMenuOptions::SetScheduler(id) => {
+                println!("Choosing a scheduler for deck with id {}", id);
+                SchedulerKind::print_menu();
+                match SchedulerKind::from_input() {
+                    Some(scheduler) => {
+                        println!("Scheduling deck with id {} with {}", id, scheduler);
+                        set_deck_scheduler(tx, id, scheduler).await?;
+                    }
+                    None => println!("Invalid scheduler"),
+                }
+                return Ok((MenuState::DeckDetailMenu(id), true));
+            }
+            DeckDetailMenuOptions::SetReviewMode(id) => {
+                println!("Choosing a review mode for deck with id {}", id);
+                ReviewMode::print_menu();
+                match ReviewMode::from_input() {
+                    Some(review_mode) => {
+                        println!("Reviewing deck with id {} in {} mode", id, review_mode);
+                        set_deck_review_mode(tx, id, review_mode).await?;
+                    }
+                    None => println!("Invalid review mode"),
+                }
+                return Ok((MenuState::DeckDetailMenu(id), true));
+            }
+            DeckDetailMenuOptions::SetGrading(id) => {
+                println!(
+                    "Choosing how typed answers are graded for deck with id {}",
+                    id
+                );
+                match prompt_for_grading()?.parse::<Grader>() {
+                    Ok(grader) => {
+                        println!("Grading deck with id {} with {}", id, grader);
+                        set_deck_grader(tx, id, &grader).await?;
+                    }
+                    Err(e) => println!("Invalid grading: {}", e),
+                }
+                return Ok((MenuState::DeckDetailMenu(id), true));
+            }
+            DeckDetailMenuOptions::RebuildFiltered(id) => {
+                if query_deck_search(tx, id).await?.is_none() {
+                    println!("Deck with id {} is not a filtered deck", id);
+                } else {
+                    let borrowed = rebuild_filtered_deck(tx, id).await?;
+                    println!("Borrowed {} cards into deck with id {}", borrowed, id);
+                }
+                return Ok((MenuState::DeckDetailMenu(id), true));
+            }
+            DeckDetailMenuOptions::EmptyFiltered(id) => {
+                if query_deck_search(tx, id).await?.is_none() {
+                    println!("Deck with id {} is not a filtered deck", id);
+                } else {
+                    let returned = empty_filtered_deck(tx, id).await?;
+                    println!("Returned {} cards from deck with id {}", returned, id);
+                }
                 return Ok((MenuState::DeckDetailMenu(id), true));
             }
             DeckDetailMenuOptions::GoBack(mut state) => {
@@ -1,11 +1,15 @@
 mod deck;
 pub mod card;
+pub mod tag;
+pub mod trash;
 pub mod traits;
 pub mod utils;
 
 use self::traits::{DecisionMaker, MenuOptions, ProcessOption};
 use super::menus::deck::{DeckDetailMenuOptions, DeckMenuOptions};
 use super::menus::card::{CardMenuOptions, CardSubMenuOptions};
+use super::menus::tag::TagMenuOptions;
+use super::menus::trash::TrashMenuOptions;
 use super::state::AppState;
 use async_trait::async_trait;
 use sqlx::{Sqlite, Transaction};
@@ -19,12 +23,18 @@ pub enum MenuState {
     DeckDetailMenu(i64),
     CardMenu,
     CardSubMenu,
+    TagMenu,
+    TrashMenu,
+    /// A review of a deck, narrowed to a tag and its subtags when one is given.
+    Review(i64, Option<i64>),
 }
 
 #[derive(EnumIter, Display, Debug, PartialEq, Clone, Copy)]
 enum MenuOption {
     DeckMenu,
     CardMenu,
+    TagMenu,
+    Trash,
     Quit,
 }
 
@@ -61,6 +71,12 @@ impl DecisionMaker for MenuState {
                     DeckDetailMenuOptions::AddCard(_) => DeckDetailMenuOptions::AddCard(id),
                     DeckDetailMenuOptions::ListAllCards(_) => DeckDetailMenuOptions::ListAllCards(id),
                     DeckDetailMenuOptions::Review(_) => DeckDetailMenuOptions::Review(id),
+                    DeckDetailMenuOptions::ReviewTag(_) => DeckDetailMenuOptions::ReviewTag(id),
+                    DeckDetailMenuOptions::SetScheduler(_) => DeckDetailMenuOptions::SetScheduler(id),
+                    DeckDetailMenuOptions::SetReviewMode(_) => DeckDetailMenuOptions::SetReviewMode(id),
+                    DeckDetailMenuOptions::SetGrading(_) => DeckDetailMenuOptions::SetGrading(id),
+                    DeckDetailMenuOptions::RebuildFiltered(_) => DeckDetailMenuOptions::RebuildFiltered(id),
+                    DeckDetailMenuOptions::EmptyFiltered(_) => DeckDetailMenuOptions::EmptyFiltered(id),
                     DeckDetailMenuOptions::CreateCard(_) => DeckDetailMenuOptions::CreateCard(id),
                     DeckDetailMenuOptions::GoBack(_) => DeckDetailMenuOptions::GoBack(state.clone()),
                     DeckDetailMenuOptions::Quit => DeckDetailMenuOptions::Quit,
@@ -80,6 +96,20 @@ impl DecisionMaker for MenuState {
                 let card_sub_menu_choice = CardSubMenuOptions::from_input().unwrap();
                 card_sub_menu_choice.process(tx, state).await
             }
+            MenuState::TagMenu => {
+                TagMenuOptions::print_menu();
+                let tag_menu_choice = TagMenuOptions::from_input().unwrap();
+                tag_menu_choice.process(tx, state).await
+            }
+            MenuState::TrashMenu => {
+                TrashMenuOptions::print_menu();
+                let trash_menu_choice = TrashMenuOptions::from_input().unwrap();
+                trash_menu_choice.process(tx, state).await
+            }
+            MenuState::Review(id, _) => {
+                // reviews are run by the app loop, which owns the connection pool
+                Ok((MenuState::DeckDetailMenu(id), true))
+            }
         }
     }
 }
@@ -105,6 +135,16 @@ impl ProcessOption for MenuOption {
                 let card_menu_choice = CardMenuOptions::from_input().unwrap();
                 return card_menu_choice.process(tx, state).await;
             }
+            MenuOption::TagMenu => {
+                TagMenuOptions::print_menu();
+                let tag_menu_choice = TagMenuOptions::from_input().unwrap();
+                return tag_menu_choice.process(tx, state).await;
+            }
+            MenuOption::Trash => {
+                TrashMenuOptions::print_menu();
+                let trash_menu_choice = TrashMenuOptions::from_input().unwrap();
+                return trash_menu_choice.process(tx, state).await;
+            }
             MenuOption::Quit => {
                 return Ok((MenuState::MainMenu, false));
             }
@@ -0,0 +1,108 @@
+use super::traits::{MenuOptions, ProcessOption};
+use super::utils::{prompt_for_confirmation, prompt_for_tag, prompt_for_tag_details};
+use super::MenuState;
+
+use crate::app::state::AppState;
+use crate::cli::is_unique_violation;
+use crate::hierarchy::normalize_name;
+use crate::output::print_rows;
+use crate::queries::{create_tag, delete_tag, list_tags, query_tag_id, update_tag};
+use async_trait::async_trait;
+use sqlx::{Sqlite, Transaction};
+
+use strum::{Display, EnumIter};
+
+#[derive(EnumIter, Display, Debug, PartialEq, Clone, Copy)]
+pub enum TagMenuOptions {
+    Create,
+    List,
+    Update,
+    Delete,
+    GoToMainMenu,
+    Quit,
+}
+
+impl MenuOptions for TagMenuOptions {}
+
+#[async_trait]
+impl ProcessOption for TagMenuOptions {
+    async fn process(
+        self,
+        tx: &mut Transaction<'_, Sqlite>,
+        state: &AppState,
+    ) -> Result<(MenuState, bool), sqlx::Error> {
+        match self {
+            TagMenuOptions::Create => {
+                println!("Creating a tag");
+                let (name, description) = prompt_for_tag_details()?;
+                let Some(name) = normalize_name(&name) else {
+                    println!("The name of a tag cannot be empty or have empty levels");
+                    return Ok((MenuState::TagMenu, true));
+                };
+                match create_tag(tx, name.clone(), description).await {
+                    Ok(id) => println!("Created tag with id: {}", id),
+                    Err(e) if is_unique_violation(&e) => {
+                        println!("A tag named {} already exists", name)
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            TagMenuOptions::List => {
+                println!("Listing all tags");
+                print_rows(&list_tags(tx).await?, state.format());
+            }
+            TagMenuOptions::Update => {
+                println!("Updating a tag");
+                let tag = prompt_for_tag()?;
+                let Some(id) = query_tag_id(tx, &tag).await? else {
+                    println!("No tag with id or name: {} found", tag);
+                    return Ok((MenuState::TagMenu, true));
+                };
+                let (name, description) = prompt_for_tag_details()?;
+                // a blank name keeps the current one
+                let name = match name.as_str() {
+                    "" => None,
+                    name => match normalize_name(name) {
+                        Some(name) => Some(name),
+                        None => {
+                            println!("The name of a tag cannot have empty levels");
+                            return Ok((MenuState::TagMenu, true));
+                        }
+                    },
+                };
+                match update_tag(tx, id, name.clone(), description).await {
+                    Ok(_) => println!("Updated tag with id: {}", id),
+                    Err(e) if is_unique_violation(&e) => {
+                        println!("A tag named {} already exists", name.unwrap_or_default())
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            TagMenuOptions::Delete => {
+                println!("Deleting a tag");
+                let tag = prompt_for_tag()?;
+                let Some(id) = query_tag_id(tx, &tag).await? else {
+                    println!("No tag with id or name: {} found", tag);
+                    return Ok((MenuState::TagMenu, true));
+                };
+                let question = format!(
+                    "Delete tag with id {} and the tags under it and take them off every card?",
+                    id
+                );
+                if prompt_for_confirmation(&question)? {
+                    delete_tag(tx, id).await?;
+                    println!("Deleted tag with id: {}", id);
+                }
+            }
+            TagMenuOptions::GoToMainMenu => {
+                println!("Going to main menu");
+                return Ok((MenuState::MainMenu, true));
+            }
+            TagMenuOptions::Quit => {
+                println!("Quitting");
+                return Ok((MenuState::MainMenu, false));
+            }
+        }
+        Ok((MenuState::TagMenu, true))
+    }
+}
@@ -17,11 +17,6 @@ pub trait MenuOptions: IntoEnumIterator + Display + Sized + PartialEq {
         }
     }
 
-    fn print(&self) -> &Self {
-        Self::print_menu();
-        self
-    }
-
     fn from_input() -> Option<Self> {
         let mut input = String::new();
 
@@ -0,0 +1,73 @@
+use super::traits::{MenuOptions, ProcessOption};
+use super::utils::{prompt_for_card_id, prompt_for_confirmation, prompt_for_deck_id};
+use super::MenuState;
+
+use crate::app::state::AppState;
+use crate::output::print_rows;
+use crate::queries::{list_trash, purge_trash, restore_card, restore_deck};
+use async_trait::async_trait;
+use sqlx::{Sqlite, Transaction};
+
+use strum::{Display, EnumIter};
+
+#[derive(EnumIter, Display, Debug, PartialEq, Clone, Copy)]
+pub enum TrashMenuOptions {
+    List,
+    RestoreCard,
+    RestoreDeck,
+    Purge,
+    GoToMainMenu,
+    Quit,
+}
+
+impl MenuOptions for TrashMenuOptions {}
+
+#[async_trait]
+impl ProcessOption for TrashMenuOptions {
+    async fn process(
+        self,
+        tx: &mut Transaction<'_, Sqlite>,
+        state: &AppState,
+    ) -> Result<(MenuState, bool), sqlx::Error> {
+        let user_id = state.user().id();
+        match self {
+            TrashMenuOptions::List => {
+                println!("Listing the trash");
+                print_rows(&list_trash(tx, user_id).await?, state.format());
+            }
+            TrashMenuOptions::RestoreCard => {
+                println!("Restoring a card");
+                let id = prompt_for_card_id()?;
+                if restore_card(tx, id).await? {
+                    println!("Restored card with id: {}", id);
+                } else {
+                    println!("No card with id: {} in the trash", id);
+                }
+            }
+            TrashMenuOptions::RestoreDeck => {
+                println!("Restoring a deck and the cards deleted with it");
+                let id = prompt_for_deck_id()?;
+                if restore_deck(tx, user_id, id).await? {
+                    println!("Restored deck with id: {}", id);
+                } else {
+                    println!("No deck with id: {} in the trash", id);
+                }
+            }
+            TrashMenuOptions::Purge => {
+                if prompt_for_confirmation("Delete everything in the trash for good?")? {
+                    let count = purge_trash(tx, user_id).await?;
+                    println!("Deleted {} card(s) and deck(s) for good", count);
+                }
+            }
+            TrashMenuOptions::GoToMainMenu => {
+                println!("Going to main menu");
+                return Ok((MenuState::MainMenu, true));
+            }
+            TrashMenuOptions::Quit => {
+                println!("Quitting");
+                return Ok((MenuState::MainMenu, false));
+            }
+        }
+        Ok((MenuState::TrashMenu, true))
+    }
+}
@@ -17,6 +17,27 @@ pub fn prompt_for_deck_details() -> Result<(String, Option<String>), io::Error>
         Some(description.trim().to_string()),
     ))
 }
+pub fn prompt_for_private_deck() -> Result<bool, io::Error> {
+    let mut private = String::new();
+
+    println!("Keep this deck private to you? (y/n): ");
+    io::stdin().read_line(&mut private)?;
+    Ok(private.trim().eq_ignore_ascii_case("y"))
+}
+pub fn prompt_for_confirmation(question: &str) -> Result<bool, io::Error> {
+    let mut answer = String::new();
+
+    println!("{} (y/n): ", question);
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("y"))
+}
+pub fn prompt_for_deck_cards_deletion() -> Result<bool, io::Error> {
+    let mut delete_cards = String::new();
+
+    println!("Also move the cards that are in no other deck to the trash? (y/n): ");
+    io::stdin().read_line(&mut delete_cards)?;
+    Ok(delete_cards.trim().eq_ignore_ascii_case("y"))
+}
 pub fn prompt_for_deck_id() -> Result<i64, io::Error> {
     let mut id = String::new();
 
@@ -44,6 +65,56 @@ pub fn prompt_for_card_details() -> Result<(Option<String>, Option<String>), io:
     }
 }
 
+pub fn prompt_for_tag() -> Result<String, io::Error> {
+    let mut tag = String::new();
+
+    println!("Tag (id or name): ");
+    io::stdin().read_line(&mut tag)?;
+    Ok(tag.trim().to_string())
+}
+
+pub fn prompt_for_tag_details() -> Result<(String, Option<String>), io::Error> {
+    let mut name = String::new();
+    let mut description = String::new();
+
+    println!("Name: ");
+    io::stdin().read_line(&mut name)?;
+
+    println!("Description: ");
+    io::stdin().read_line(&mut description)?;
+
+    let description = description.trim();
+    Ok((
+        name.trim().to_string(),
+        Some(description.to_string()).filter(|description| !description.is_empty()),
+    ))
+}
+
+pub fn prompt_for_search() -> Result<String, io::Error> {
+    let mut search = String::new();
+
+    println!("Search (like deck:Spanish tag:verbs is:due front:*ar -is:suspended): ");
+    io::stdin().read_line(&mut search)?;
+    Ok(search.trim().to_string())
+}
+
+pub fn prompt_for_search_limit() -> Result<Option<i64>, io::Error> {
+    let mut limit = String::new();
+
+    println!("Most cards to borrow (blank for all): ");
+    io::stdin().read_line(&mut limit)?;
+    Ok(limit.trim().parse().ok().filter(|limit| *limit > 0))
+}
+
+pub fn prompt_for_grading() -> Result<String, io::Error> {
+    let mut grading = String::new();
+
+    println!("Normalizers (casefold, accents, punctuation) followed by a comparator");
+    println!("(exact, levenshtein=0.8, damerau=0.8, tokens=0.5, numeric=0.01), comma separated: ");
+    io::stdin().read_line(&mut grading)?;
+    Ok(grading.trim().to_string())
+}
+
 pub fn prompt_for_card_id() -> Result<i64, io::Error> {
     let mut id = String::new();
     println!("ID: ");
@@ -6,14 +6,18 @@ use std::io::{self, Write};
 use std::process::Command;
 
 use crate::auth::login;
+use crate::guest::start_guest_collection;
 use crate::app::menus::traits::DecisionMaker;
+use crate::app::menus::MenuState;
 use crate::app::state::AppState;
 use crate::models::User;
+use crate::output::OutputFormat;
+use crate::review::{resume_interrupted_session, review_deck, ReviewOptions, Terminal};
 
 fn _clear_screen() {
     if cfg!(target_os = "windows") {
         Command::new("cmd")
-            .args(&["/C", "cls"])
+            .args(["/C", "cls"])
             .spawn()
             .unwrap()
             .wait()
@@ -24,11 +28,11 @@ fn _clear_screen() {
     }
 }
 
-pub async fn start_app(pool: SqlitePool) -> Result<(), sqlx::Error> {
+pub async fn start_app(pool: SqlitePool, format: OutputFormat) -> Result<(), sqlx::Error> {
     println!("Starting app");
 
     // log in
-    let user: User = match login() {
+    let user: User = match login(&pool).await {
         Ok(user) => user,
         Err(e) => {
             panic!("Error logging in {}", e);
@@ -37,22 +41,48 @@ pub async fn start_app(pool: SqlitePool) -> Result<(), sqlx::Error> {
 
     println!("Logged in as {:?}", user);
 
-    let mut app_state = AppState::new(user);
+    // guests study in a collection of their own, away from the data of real users
+    let guest = if user.is_guest() {
+        Some(start_guest_collection(&pool).await?)
+    } else {
+        None
+    };
+    let app_pool = match &guest {
+        Some(guest) => guest.pool.clone(),
+        None => pool.clone(),
+    };
+
+    resume_interrupted_session(&app_pool, &user).await?;
+
+    let mut app_state = AppState::new(user, format);
 
     loop {
-        let mut tx = pool.begin().await?;
+        if let MenuState::Review(deck_id, tag_id) = app_state.current_menu {
+            let options = ReviewOptions {
+                tag: tag_id,
+                ..Default::default()
+            };
+            review_deck(&app_pool, app_state.user(), deck_id, &options, &mut Terminal).await?;
+            app_state.current_menu = app_state.get_previous_menu();
+            continue;
+        }
+
+        let mut tx = app_pool.begin().await?;
         let (next_state, should_continue) = app_state.current_menu.make_decision(&mut tx, &app_state).await?;
         app_state.navigate(next_state);
+        tx.commit().await?;
 
         if !should_continue {
             break;
         }
 
-        tx.commit().await?;
-
         println!("State: {:?}", app_state);
     }
 
+    if let Some(guest) = guest {
+        guest.offer_claim(&pool).await?;
+    }
+
     Ok(())
 }
 
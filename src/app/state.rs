@@ -1,29 +1,41 @@
 use crate::app::menus::MenuState;
 use crate::models::User;
+use crate::output::OutputFormat;
 use colored::*;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct AppState {
     pub current_menu: MenuState,
     navigation_stack: Vec<MenuState>,
-    user: User
+    user: User,
+    format: OutputFormat
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        Self::new(User::guest())
+        Self::new(User::guest(), OutputFormat::default())
     }
 }
 
 impl AppState {
-    pub fn new(user: User) -> Self {
+    pub fn new(user: User, format: OutputFormat) -> Self {
         Self {
             current_menu: MenuState::MainMenu,
             navigation_stack: vec![MenuState::MainMenu],
-            user
+            user,
+            format
         }
     }
 
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    /// How listings are printed in the menus.
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+
     pub fn navigate(&mut self, new_menu: MenuState) {
         println!("{}", format!("Navigating to {:?}", new_menu).red().bold());
         if self.current_menu == new_menu {
@@ -37,7 +49,7 @@ impl AppState {
         // print navigation stack
         println!("{}", format!("Navigation stack {:?}", self.navigation_stack).green().bold());
         self.navigation_stack.pop();
-        let previous_menu = self.navigation_stack.last().unwrap().clone();
+        let previous_menu = *self.navigation_stack.last().unwrap();
         println!("{}", format!("Previous menu {:?}", previous_menu).green().bold());
         previous_menu
     }
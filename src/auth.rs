@@ -1,25 +1,120 @@
-use std::io;
+use std::io::{self, Write};
+
 use crate::models::User;
+use crate::queries::{create_user, query_user_credentials};
+use bcrypt::verify;
+use sqlx::SqlitePool;
+use strum::{Display, EnumIter, IntoEnumIterator};
 
-pub fn login() -> Result<User, Box<dyn std::error::Error>> {
-    let mut username = String::new();
-    let mut password = String::new();
+#[derive(EnumIter, Display, Debug, PartialEq, Clone, Copy)]
+enum LoginOption {
+    Login,
+    Register,
+    Guest,
+}
 
-    println!("Please enter your username:");
-    io::stdin().read_line(&mut username)?;
-    println!("Please enter your password:");
-    io::stdin().read_line(&mut password)?;
+pub fn prompt(message: &str) -> Result<String, io::Error> {
+    let mut input = String::new();
 
-    username = username.trim().to_string();
-    password = password.trim().to_string();
+    println!("{}", message);
+    io::stdout().flush()?;
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stdin was closed while logging in",
+        ));
+    }
 
-    let user = User::find_by_username(username.to_string())?;
+    Ok(input.trim().to_string())
+}
 
-    if user.authenticate(password) {
-        println!("User is {:?} authenticated", user);
-        return Ok(user);
+fn prompt_for_login_option() -> Result<LoginOption, io::Error> {
+    loop {
+        println!("Would you like to log in, register or continue as a guest?");
+        for (index, option) in LoginOption::iter().enumerate() {
+            println!("{}. {}", index + 1, option);
+        }
+        let choice = prompt("Please enter a command: ")?;
+        let option = choice
+            .parse::<usize>()
+            .ok()
+            .and_then(|choice| LoginOption::iter().nth(choice.checked_sub(1)?));
+        match option {
+            Some(option) => return Ok(option),
+            None => println!("Invalid choice"),
+        }
     }
+}
+
+/// Checks a username and password against the `user` table.
+pub async fn authenticate(
+    pool: &SqlitePool,
+    username: &str,
+    password: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let credentials = query_user_credentials(&mut tx, username).await?;
+    tx.commit().await?;
+
+    Ok(credentials
+        .filter(|credentials| verify(password, &credentials.password_hash).unwrap_or(false))
+        .map(|credentials| User::authenticated(credentials.id, credentials.username)))
+}
 
-    println!("User is not authenticated");
-    Ok(User::guest())
+/// Creates a new account, returning `None` when the username is already taken.
+pub async fn register(
+    pool: &SqlitePool,
+    username: String,
+    password: String,
+) -> Result<Option<User>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if query_user_credentials(&mut tx, &username).await?.is_some() {
+        return Ok(None);
+    }
+    let id = create_user(&mut tx, username.clone(), password).await?;
+    tx.commit().await?;
+
+    Ok(Some(User::authenticated(id, username)))
+}
+
+pub async fn login(pool: &SqlitePool) -> Result<User, Box<dyn std::error::Error>> {
+    loop {
+        match prompt_for_login_option()? {
+            LoginOption::Login => {
+                let username = prompt("Please enter your username:")?;
+                let password = prompt("Please enter your password:")?;
+
+                match authenticate(pool, &username, &password).await? {
+                    Some(user) => {
+                        println!("User is {:?} authenticated", user);
+                        return Ok(user);
+                    }
+                    None => println!("Invalid username or password"),
+                }
+            }
+            LoginOption::Register => {
+                let username = prompt("Please choose a username:")?;
+                let password = prompt("Please choose a password:")?;
+                let confirmation = prompt("Please repeat the password:")?;
+
+                if username.is_empty() || password.is_empty() {
+                    println!("The username and password cannot be empty");
+                } else if password != confirmation {
+                    println!("The passwords do not match");
+                } else {
+                    match register(pool, username, password).await? {
+                        Some(user) => {
+                            println!("Registered {:?}", user);
+                            return Ok(user);
+                        }
+                        None => println!("That username is already taken"),
+                    }
+                }
+            }
+            LoginOption::Guest => {
+                println!("Continuing as a guest");
+                return Ok(User::guest());
+            }
+        }
+    }
 }
@@ -0,0 +1,216 @@
+use std::process::ExitCode;
+
+use super::tag::no_tag;
+use super::{authenticate_user, fail};
+use crate::hierarchy::normalize_name;
+use crate::output::{print_rows, OutputFormat};
+use crate::queries::{
+    create_card, create_tag, delete_card, list_cards, query_card_exists, query_tag_id,
+    search_cards, set_card_suspended, tag_card, untag_card, update_card,
+};
+use crate::search::Search;
+use clap::{ArgGroup, Subcommand};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+#[derive(Subcommand)]
+pub enum CardCommands {
+    /// lists all cards
+    List,
+    /// creates a new card and prints its id
+    Create {
+        /// the front of the card
+        #[arg(short, long)]
+        front: String,
+
+        /// the back of the card
+        #[arg(short, long)]
+        back: String,
+    },
+    /// updates an existing cards
+    #[command(group(ArgGroup::new("changes").required(true).multiple(true).args(["front", "back"])))]
+    Update {
+        /// the id of the card
+        #[arg(short, long)]
+        id: i64,
+
+        /// the front of the card
+        #[arg(short, long)]
+        front: Option<String>,
+
+        /// the back of the card
+        #[arg(short, long)]
+        back: Option<String>,
+    },
+    /// moves an existing card to the trash
+    Delete {
+        /// the id of the card
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// puts a tag on a card, creating the tag if there is none with that id or name
+    Tag {
+        /// the id of the card
+        #[arg(short, long)]
+        id: i64,
+
+        /// the id or name of the tag
+        #[arg(short, long)]
+        tag: String,
+    },
+    /// takes a tag off a card
+    Untag {
+        /// the id of the card
+        #[arg(short, long)]
+        id: i64,
+
+        /// the id or name of the tag
+        #[arg(short, long)]
+        tag: String,
+    },
+    /// lists the cards matching a search such as `deck:Spanish tag:verbs is:due -is:suspended`
+    ///
+    /// Words are looked up on both sides of a card regardless of case and accents, with the
+    /// best matches first and a snippet of where they were found. The other terms are front:,
+    /// back:, deck:, tag:, is:new, is:due, is:suspended, added:DAYS, rated:DAYS or
+    /// rated:DAYS:GRADE with grades from 1 (again) to 4 (easy), and prop: with ease, ivl, reps,
+    /// due, stability or difficulty compared to a number, as in prop:ease<2.0. Terms can be
+    /// negated with -, combined with or and grouped with parentheses, and * matches any text.
+    Search {
+        /// the search, whose words are joined with spaces
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        query: Vec<String>,
+
+        /// search the schedules, answers and private decks of this user, with the password
+        /// from ANKIRS_PASSWORD or asked for
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// keeps a card out of reviews until it is unsuspended
+    Suspend {
+        /// the id of the card
+        #[arg(short, long)]
+        id: i64,
+    },
+    /// brings a suspended card back into reviews
+    Unsuspend {
+        /// the id of the card
+        #[arg(short, long)]
+        id: i64,
+    },
+}
+
+/// Runs a card command in a transaction that is only committed when it succeeds.
+pub async fn run(pool: &SqlitePool, command: CardCommands, format: OutputFormat) -> ExitCode {
+    let result = async {
+        let user_id = match &command {
+            CardCommands::Search {
+                user: Some(username),
+                ..
+            } => match authenticate_user(pool, username).await? {
+                Some(user) => user.id(),
+                None => return Ok(fail("invalid username or password")),
+            },
+            _ => None,
+        };
+
+        let mut tx = pool.begin().await?;
+        let code = execute(&mut tx, command, format, user_id).await?;
+        if code == ExitCode::SUCCESS {
+            tx.commit().await?;
+        }
+        Ok::<_, sqlx::Error>(code)
+    };
+
+    match result.await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn execute(
+    tx: &mut Transaction<'_, Sqlite>,
+    command: CardCommands,
+    format: OutputFormat,
+    user_id: Option<i64>,
+) -> Result<ExitCode, sqlx::Error> {
+    match command {
+        CardCommands::List => print_rows(&list_cards(tx).await?, format),
+        CardCommands::Create { front, back } => {
+            if front.trim().is_empty() || back.trim().is_empty() {
+                return Ok(fail("the front and back of a card cannot be empty"));
+            }
+            let id = create_card(tx, front, back).await?;
+            println!("{}", id);
+        }
+        CardCommands::Update { id, front, back } => {
+            if [&front, &back]
+                .into_iter()
+                .flatten()
+                .any(|side| side.trim().is_empty())
+            {
+                return Ok(fail("the front and back of a card cannot be empty"));
+            }
+            if !update_card(tx, id, front, back).await? {
+                return Ok(fail(format!("no card with id {}", id)));
+            }
+            println!("Updated card with id: {}", id);
+        }
+        CardCommands::Delete { id } => {
+            if !delete_card(tx, id).await? {
+                return Ok(fail(format!("no card with id {}", id)));
+            }
+            println!("Moved card with id {} to the trash", id);
+        }
+        CardCommands::Tag { id, tag } => {
+            if !query_card_exists(tx, id).await? {
+                return Ok(fail(format!("no card with id {}", id)));
+            }
+            let tag_id = match query_tag_id(tx, &tag).await? {
+                Some(tag_id) => tag_id,
+                None => match normalize_name(&tag) {
+                    Some(name) => create_tag(tx, name, None).await?,
+                    None => {
+                        return Ok(fail(
+                            "the name of a tag cannot be empty or have empty levels",
+                        ))
+                    }
+                },
+            };
+            tag_card(tx, id, tag_id).await?;
+            println!("Tagged card with id {} with tag with id {}", id, tag_id);
+        }
+        CardCommands::Untag { id, tag } => {
+            let Some(tag_id) = query_tag_id(tx, &tag).await? else {
+                return Ok(no_tag(&tag));
+            };
+            if !untag_card(tx, id, tag_id).await? {
+                return Ok(fail(format!(
+                    "card with id {} does not have tag with id {}",
+                    id, tag_id
+                )));
+            }
+            println!("Took tag with id {} off card with id {}", tag_id, id);
+        }
+        CardCommands::Search { query, .. } => {
+            let search: Search = match query.join(" ").parse() {
+                Ok(search) => search,
+                Err(e) => return Ok(fail(format!("invalid search: {}", e))),
+            };
+            print_rows(&search_cards(tx, user_id, &search).await?, format);
+        }
+        CardCommands::Suspend { id } => {
+            if !set_card_suspended(tx, id, true).await? {
+                return Ok(fail(format!("no card with id {}", id)));
+            }
+            println!("Suspended card with id: {}", id);
+        }
+        CardCommands::Unsuspend { id } => {
+            if !set_card_suspended(tx, id, false).await? {
+                return Ok(fail(format!("no card with id {}", id)));
+            }
+            println!("Unsuspended card with id: {}", id);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::process::ExitCode;
+
+use super::fail;
+use crate::db::MIGRATOR;
+use crate::output::{print_rows, Listing, OutputFormat};
+use clap::Subcommand;
+use serde::Serialize;
+use sqlx::migrate::{AppliedMigration, Migrate};
+use sqlx::SqlitePool;
+
+/// Database commands are the only ones that do not migrate the database before they run.
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// lists the migrations and whether they were applied
+    Status,
+    /// applies the migrations that were not applied yet
+    Migrate,
+    /// reverts the last applied migration
+    Rollback {
+        /// revert every migration after this version instead
+        #[arg(short, long)]
+        to: Option<i64>,
+    },
+}
+
+/// A migration embedded in the binary or applied to the database.
+#[derive(Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    /// `applied`, `pending`, `changed` when the applied script differs from the embedded one,
+    /// or `unknown` when it was applied by a newer version of ankirs.
+    pub status: String,
+}
+
+impl Listing for MigrationStatus {
+    const HEADERS: &'static [&'static str] = &["version", "description", "status"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.version.to_string(),
+            self.description.clone(),
+            self.status.clone(),
+        ]
+    }
+}
+
+pub async fn run(pool: &SqlitePool, command: DbCommands, format: OutputFormat) -> ExitCode {
+    match execute(pool, command, format).await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn applied_migrations(pool: &SqlitePool) -> Result<Vec<AppliedMigration>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    Ok(conn.list_applied_migrations().await?)
+}
+
+async fn execute(
+    pool: &SqlitePool,
+    command: DbCommands,
+    format: OutputFormat,
+) -> Result<ExitCode, sqlx::Error> {
+    let applied = applied_migrations(pool).await?;
+    match command {
+        DbCommands::Status => {
+            let mut checksums: HashMap<i64, _> = applied
+                .into_iter()
+                .map(|migration| (migration.version, migration.checksum))
+                .collect();
+            let mut rows: Vec<MigrationStatus> = MIGRATOR
+                .iter()
+                .filter(|migration| !migration.migration_type.is_down_migration())
+                .map(|migration| {
+                    let status = match checksums.remove(&migration.version) {
+                        Some(checksum) if checksum == migration.checksum => "applied",
+                        Some(_) => "changed",
+                        None => "pending",
+                    };
+                    MigrationStatus {
+                        version: migration.version,
+                        description: migration.description.to_string(),
+                        status: status.to_string(),
+                    }
+                })
+                .collect();
+            rows.extend(checksums.into_keys().map(|version| MigrationStatus {
+                version,
+                description: String::new(),
+                status: "unknown".to_string(),
+            }));
+            rows.sort_by_key(|row| row.version);
+            print_rows(&rows, format);
+        }
+        DbCommands::Migrate => {
+            MIGRATOR.run(pool).await?;
+            let count = applied_migrations(pool).await?.len() - applied.len();
+            println!("Applied {} migration(s)", count);
+        }
+        DbCommands::Rollback { to } => {
+            let mut versions: Vec<i64> =
+                applied.iter().map(|migration| migration.version).collect();
+            versions.sort();
+            let target = match to {
+                Some(target) => target,
+                None => match versions.iter().rev().nth(1) {
+                    Some(previous) => *previous,
+                    None if versions.is_empty() => return Ok(fail("no migration was applied")),
+                    None => 0,
+                },
+            };
+
+            let reverted: Vec<i64> = versions
+                .into_iter()
+                .filter(|version| *version > target)
+                .collect();
+            for version in &reverted {
+                let reversible = MIGRATOR.iter().any(|migration| {
+                    migration.version == *version && migration.migration_type.is_down_migration()
+                });
+                if !reversible {
+                    return Ok(fail(format!("migration {} cannot be reverted", version)));
+                }
+            }
+            MIGRATOR.undo(pool, target).await?;
+            println!("Reverted {} migration(s)", reverted.len());
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,344 @@
+use std::process::ExitCode;
+
+use super::{authenticate_user, fail, is_unique_violation};
+use crate::hierarchy::normalize_name;
+use crate::output::{print_rows, OutputFormat};
+use crate::queries::{
+    add_card_to_deck, create_deck, create_filtered_deck, delete_deck, empty_filtered_deck,
+    list_cards_for_deck, list_decks, query_card_exists, query_deck_id, query_deck_info,
+    query_deck_search, rebuild_filtered_deck, remove_card_from_deck, update_deck, DeckError,
+};
+use crate::search::Search;
+use clap::{ArgGroup, Subcommand};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+/// Deck commands work on the shared decks, private decks are only reachable after logging in
+/// or, for filtered decks, with `--user`.
+/// Decks nest with `::`, so `Languages::Spanish` is a subdeck of `Languages`.
+#[derive(Subcommand)]
+pub enum DeckCommands {
+    /// lists all decks as a tree, counting the cards of subdecks in their parents
+    List,
+    /// creates a new deck, along with the decks above it, and prints its id
+    Create {
+        /// the name of the deck
+        #[arg(short, long)]
+        name: String,
+
+        /// the description of the deck
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// creates a filtered deck that borrows the cards matching a search from their home decks
+    /// for a cram session, and prints its id
+    ///
+    /// Borrowed cards stay in their home decks but are only reviewed in the filtered deck,
+    /// where they are all due until they are remembered and go home. See `card search --help`
+    /// for the search terms, e.g. `rated:3:1` for the cards failed in the last 3 days. The
+    /// search needs a user to look at their schedules and answers.
+    Filter {
+        /// the name of the deck
+        #[arg(short, long)]
+        name: String,
+
+        /// the description of the deck
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// the search that picks the cards to borrow
+        #[arg(short, long, allow_hyphen_values = true)]
+        search: String,
+
+        /// borrow at most this many cards, the ones due the longest first
+        #[arg(short, long)]
+        limit: Option<i64>,
+
+        /// make the deck private to this user and search their cards, with the password from ANKIRS_PASSWORD or asked for
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// gives the cards of a filtered deck back and borrows the cards matching its search again
+    Rebuild {
+        /// the id or name of the deck
+        deck: String,
+
+        /// the user the deck is private to, with the password from ANKIRS_PASSWORD or asked for
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// gives the cards of a filtered deck back to their home decks
+    Empty {
+        /// the id or name of the deck
+        deck: String,
+
+        /// the user the deck is private to, with the password from ANKIRS_PASSWORD or asked for
+        #[arg(short, long)]
+        user: Option<String>,
+    },
+    /// renames a deck and its subdecks or changes its description
+    #[command(group(ArgGroup::new("changes").required(true).multiple(true).args(["name", "description"])))]
+    Update {
+        /// the id or name of the deck
+        deck: String,
+
+        /// the new name of the deck
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// the new description of the deck, an empty one clears it
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// moves a deck and its subdecks to the trash, keeping their cards unless asked otherwise
+    ///
+    /// Filtered decks give their cards back when they are moved to the trash.
+    Delete {
+        /// the id or name of the deck
+        deck: String,
+
+        /// also move the cards of the decks that are in no other deck to the trash
+        #[arg(long)]
+        with_cards: bool,
+    },
+    /// shows a deck and its cards, or only its cards in formats other than table
+    Show {
+        /// the id or name of the deck
+        deck: String,
+    },
+    /// adds an existing card to a deck
+    AddCard {
+        /// the id or name of the deck
+        deck: String,
+
+        /// the id of the card
+        #[arg(short, long)]
+        card: i64,
+    },
+    /// takes a card out of a deck without deleting the card
+    RemoveCard {
+        /// the id or name of the deck
+        deck: String,
+
+        /// the id of the card
+        #[arg(short, long)]
+        card: i64,
+    },
+}
+
+/// Runs a deck command in a transaction that is only committed when it succeeds.
+pub async fn run(pool: &SqlitePool, command: DeckCommands, format: OutputFormat) -> ExitCode {
+    let result = async {
+        let user_id = match &command {
+            DeckCommands::Filter {
+                user: Some(username),
+                ..
+            }
+            | DeckCommands::Rebuild {
+                user: Some(username),
+                ..
+            }
+            | DeckCommands::Empty {
+                user: Some(username),
+                ..
+            } => match authenticate_user(pool, username).await? {
+                Some(user) => user.id(),
+                None => return Ok(fail("invalid username or password")),
+            },
+            _ => None,
+        };
+
+        let mut tx = pool.begin().await?;
+        let code = execute(&mut tx, command, format, user_id).await?;
+        if code == ExitCode::SUCCESS {
+            tx.commit().await?;
+        }
+        Ok::<_, sqlx::Error>(code)
+    };
+
+    match result.await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn execute(
+    tx: &mut Transaction<'_, Sqlite>,
+    command: DeckCommands,
+    format: OutputFormat,
+    user_id: Option<i64>,
+) -> Result<ExitCode, sqlx::Error> {
+    match command {
+        DeckCommands::List => print_rows(&list_decks(tx, None).await?, format),
+        DeckCommands::Create { name, description } => {
+            let Some(name) = normalize_name(&name) else {
+                return Ok(fail(
+                    "the name of a deck cannot be empty or have empty levels",
+                ));
+            };
+            match create_deck(tx, name.clone(), description, None).await {
+                Ok(id) => println!("{}", id),
+                Err(DeckError::Database(e)) if is_unique_violation(&e) => {
+                    return Ok(name_taken(&name))
+                }
+                Err(DeckError::Database(e)) => return Err(e),
+                Err(e) => return Ok(fail(e)),
+            }
+        }
+        DeckCommands::Filter {
+            name,
+            description,
+            search,
+            limit,
+            ..
+        } => {
+            let Some(name) = normalize_name(&name) else {
+                return Ok(fail(
+                    "the name of a deck cannot be empty or have empty levels",
+                ));
+            };
+            if let Err(e) = search.parse::<Search>() {
+                return Ok(fail(format!("invalid search: {}", e)));
+            }
+            if limit.is_some_and(|limit| limit < 1) {
+                return Ok(fail("the limit must be at least 1"));
+            }
+            let id =
+                match create_filtered_deck(tx, name.clone(), description, search, limit, user_id)
+                    .await
+                {
+                    Ok(id) => id,
+                    Err(DeckError::Database(e)) if is_unique_violation(&e) => {
+                        return Ok(name_taken(&name))
+                    }
+                    Err(DeckError::Database(e)) => return Err(e),
+                    Err(e) => return Ok(fail(e)),
+                };
+            rebuild_filtered_deck(tx, id).await?;
+            println!("{}", id);
+        }
+        DeckCommands::Rebuild { deck, .. } => {
+            let Some(id) = query_filtered_deck_id(tx, user_id, &deck).await? else {
+                return Ok(not_filtered(&deck));
+            };
+            let borrowed = rebuild_filtered_deck(tx, id).await?;
+            println!("Borrowed {} cards into deck with id {}", borrowed, id);
+        }
+        DeckCommands::Empty { deck, .. } => {
+            let Some(id) = query_filtered_deck_id(tx, user_id, &deck).await? else {
+                return Ok(not_filtered(&deck));
+            };
+            let returned = empty_filtered_deck(tx, id).await?;
+            println!("Returned {} cards from deck with id {}", returned, id);
+        }
+        DeckCommands::Update {
+            deck,
+            name,
+            description,
+        } => {
+            let Some(id) = query_deck_id(tx, None, &deck).await? else {
+                return Ok(no_deck(&deck));
+            };
+            let name = match name.as_deref().map(normalize_name) {
+                Some(None) => {
+                    return Ok(fail(
+                        "the name of a deck cannot be empty or have empty levels",
+                    ))
+                }
+                name => name.flatten(),
+            };
+            let description = description.map(|description| match description.trim() {
+                "" => None,
+                description => Some(description.to_string()),
+            });
+            match update_deck(tx, None, id, name.clone(), description).await {
+                Ok(_) => println!("Updated deck with id: {}", id),
+                Err(DeckError::Database(e)) if is_unique_violation(&e) => {
+                    return Ok(name_taken(name.as_deref().unwrap_or_default()))
+                }
+                Err(DeckError::Database(e)) => return Err(e),
+                Err(e) => return Ok(fail(e)),
+            }
+        }
+        DeckCommands::Delete { deck, with_cards } => {
+            let Some(id) = query_deck_id(tx, None, &deck).await? else {
+                return Ok(no_deck(&deck));
+            };
+            delete_deck(tx, None, id, with_cards).await?;
+            println!("Moved deck with id {} to the trash", id);
+        }
+        DeckCommands::Show { deck } => {
+            let Some(id) = query_deck_id(tx, None, &deck).await? else {
+                return Ok(no_deck(&deck));
+            };
+            // only tables have room for the deck itself, other formats stay parseable
+            if format == OutputFormat::Table {
+                println!("{}", query_deck_info(tx, id).await);
+            }
+            print_rows(&list_cards_for_deck(tx, id).await?, format);
+        }
+        DeckCommands::AddCard { deck, card } => {
+            let Some(id) = query_deck_id(tx, None, &deck).await? else {
+                return Ok(no_deck(&deck));
+            };
+            if query_deck_search(tx, id).await?.is_some() {
+                return Ok(fail(format!(
+                    "deck with id {} is a filtered deck, rebuild it to change its cards",
+                    id
+                )));
+            }
+            if !query_card_exists(tx, card).await? {
+                return Ok(fail(format!("no card with id {}", card)));
+            }
+            match add_card_to_deck(tx, card, id).await {
+                Ok(()) => println!("Added card with id {} to deck with id {}", card, id),
+                Err(e) if is_unique_violation(&e) => {
+                    return Ok(fail(format!(
+                        "card with id {} is already in deck with id {}",
+                        card, id
+                    )))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        DeckCommands::RemoveCard { deck, card } => {
+            let Some(id) = query_deck_id(tx, None, &deck).await? else {
+                return Ok(no_deck(&deck));
+            };
+            if !remove_card_from_deck(tx, card, id).await? {
+                return Ok(fail(format!(
+                    "card with id {} is not in deck with id {}",
+                    card, id
+                )));
+            }
+            println!("Removed card with id {} from deck with id {}", card, id);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Looks a deck `user_id` can see up by id or name, returning it only when it is a filtered
+/// deck.
+async fn query_filtered_deck_id(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let Some(id) = query_deck_id(tx, user_id, deck).await? else {
+        return Ok(None);
+    };
+
+    Ok(query_deck_search(tx, id).await?.map(|_| id))
+}
+
+fn not_filtered(deck: &str) -> ExitCode {
+    fail(format!("no filtered deck with id or name {}", deck))
+}
+
+fn no_deck(deck: &str) -> ExitCode {
+    fail(format!("no deck with id or name {}", deck))
+}
+
+fn name_taken(name: &str) -> ExitCode {
+    fail(format!("a deck named {} already exists", name))
+}
@@ -0,0 +1,44 @@
+pub mod card;
+pub mod db;
+pub mod deck;
+pub mod optimize;
+pub mod review;
+pub mod tag;
+pub mod trash;
+
+use std::env;
+use std::fmt::Display;
+use std::process::ExitCode;
+
+use crate::auth::{authenticate, prompt};
+use crate::models::User;
+use sqlx::SqlitePool;
+
+/// The variable a password is read from before falling back to asking for it.
+pub const PASSWORD_VARIABLE: &str = "ANKIRS_PASSWORD";
+
+/// Reports why a command failed on stderr and returns the exit code for failures.
+pub fn fail(message: impl Display) -> ExitCode {
+    eprintln!("error: {}", message);
+    ExitCode::FAILURE
+}
+
+/// Logs in a user named with `--user`, with the password from ANKIRS_PASSWORD or asked for,
+/// returning `None` when the username or password is wrong.
+pub async fn authenticate_user(
+    pool: &SqlitePool,
+    username: &str,
+) -> Result<Option<User>, sqlx::Error> {
+    let password = match env::var(PASSWORD_VARIABLE) {
+        Ok(password) => password,
+        Err(_) => prompt("Please enter your password:")?,
+    };
+
+    authenticate(pool, username, &password).await
+}
+
+/// Deck and tag names are unique, and so is a card in a deck, so a clash shows up as a
+/// constraint violation.
+pub fn is_unique_violation(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(e) if e.is_unique_violation())
+}
@@ -0,0 +1,72 @@
+use std::process::ExitCode;
+
+use super::fail;
+use crate::queries::{optimize_fsrs, query_deck_id, query_user_id};
+use crate::scheduler::optimizer::{format_weights, MIN_REVIEWS};
+use clap::Args;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+#[derive(Args)]
+pub struct OptimizeArgs {
+    /// only use the answers of this user
+    #[arg(short, long)]
+    user: Option<String>,
+
+    /// only use the answers given in this deck, by id or name
+    #[arg(short, long)]
+    deck: Option<String>,
+}
+
+/// Fits FSRS weights in a transaction that is only committed when it succeeds.
+pub async fn run(pool: &SqlitePool, args: OptimizeArgs) -> ExitCode {
+    let result = async {
+        let mut tx = pool.begin().await?;
+        let code = execute(&mut tx, args).await?;
+        if code == ExitCode::SUCCESS {
+            tx.commit().await?;
+        }
+        Ok::<_, sqlx::Error>(code)
+    };
+
+    match result.await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn execute(
+    tx: &mut Transaction<'_, Sqlite>,
+    args: OptimizeArgs,
+) -> Result<ExitCode, sqlx::Error> {
+    let user_id = match &args.user {
+        Some(username) => match query_user_id(tx, username).await? {
+            Some(id) => Some(id),
+            None => return Ok(fail(format!("no user named {}", username))),
+        },
+        None => None,
+    };
+    // the private decks of the user can be named too
+    let deck_id = match &args.deck {
+        Some(deck) => match query_deck_id(tx, user_id, deck).await? {
+            Some(id) => Some(id),
+            None => return Ok(fail(format!("no deck with id or name {}", deck))),
+        },
+        None => None,
+    };
+
+    match optimize_fsrs(tx, user_id, deck_id).await? {
+        Some(result) => {
+            println!(
+                "Log loss went from {:.4} to {:.4} over {} reviews",
+                result.initial_log_loss, result.log_loss, result.review_count
+            );
+            println!("Weights: {}", format_weights(&result.weights));
+        }
+        None => println!(
+            "Not enough review history, at least {} reviews on later days are needed",
+            MIN_REVIEWS
+        ),
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
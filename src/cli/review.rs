@@ -0,0 +1,113 @@
+use std::env;
+use std::io;
+use std::process::ExitCode;
+
+use super::{authenticate_user, fail, PASSWORD_VARIABLE};
+use crate::guest::GuestCollection;
+use crate::models::User;
+use crate::protocol::{JsonLines, Protocol};
+use crate::queries::{query_deck_id, query_tag_id};
+use crate::review::{review_deck, ReviewFrontend, ReviewMode, ReviewOptions, Terminal};
+use clap::Args;
+use sqlx::SqlitePool;
+
+#[derive(Args)]
+pub struct ReviewArgs {
+    /// the id or name of the deck, whose subdecks are reviewed along with it
+    #[arg(short, long)]
+    deck: String,
+
+    /// review at most this many cards
+    #[arg(short, long)]
+    limit: Option<usize>,
+
+    /// review in this mode instead of the one of the deck
+    #[arg(short, long, value_enum)]
+    mode: Option<ReviewMode>,
+
+    /// only review the cards with this tag or a tag under it, by id or name
+    #[arg(short, long)]
+    tag: Option<String>,
+
+    /// review at most this many cards that were never reviewed before
+    #[arg(short, long)]
+    new: Option<usize>,
+
+    /// review as this user, with the password from ANKIRS_PASSWORD or asked for;
+    /// without a user the review happens in a guest collection that is thrown away unless
+    /// it is claimed at the end, so --protocol jsonl needs a user
+    #[arg(short, long)]
+    user: Option<String>,
+
+    /// how cards are shown and answers are read
+    #[arg(long, value_enum, default_value_t = Protocol::Terminal)]
+    protocol: Protocol,
+}
+
+/// Reviews a deck and exits once the session is over.
+pub async fn run(pool: &SqlitePool, args: ReviewArgs) -> ExitCode {
+    match execute(pool, args).await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn execute(pool: &SqlitePool, args: ReviewArgs) -> Result<ExitCode, sqlx::Error> {
+    let user = match &args.user {
+        Some(username) => {
+            // stdin belongs to the other program when it drives the review
+            if args.protocol == Protocol::Jsonl && env::var(PASSWORD_VARIABLE).is_err() {
+                return Ok(fail(format!(
+                    "{} must be set to review as a user with --protocol jsonl",
+                    PASSWORD_VARIABLE
+                )));
+            }
+            match authenticate_user(pool, username).await? {
+                Some(user) => user,
+                None => return Ok(fail("invalid username or password")),
+            }
+        }
+        // a program driving the review cannot claim a guest collection, which would throw
+        // away every answer
+        None if args.protocol == Protocol::Jsonl => {
+            return Ok(fail(
+                "--protocol jsonl needs a --user to keep the answers and schedules",
+            ))
+        }
+        None => User::guest(),
+    };
+
+    let mut tx = pool.begin().await?;
+    let Some(deck_id) = query_deck_id(&mut tx, user.id(), &args.deck).await? else {
+        return Ok(fail(format!("no deck with id or name {}", args.deck)));
+    };
+    let tag_id = match &args.tag {
+        Some(tag) => match query_tag_id(&mut tx, tag).await? {
+            Some(id) => Some(id),
+            None => return Ok(fail(format!("no tag with id or name {}", tag))),
+        },
+        None => None,
+    };
+    tx.commit().await?;
+
+    let options = ReviewOptions {
+        limit: args.limit,
+        new: args.new,
+        mode: args.mode,
+        tag: tag_id,
+    };
+    let mut frontend: Box<dyn ReviewFrontend> = match args.protocol {
+        Protocol::Terminal => Box::new(Terminal),
+        Protocol::Jsonl => Box::new(JsonLines::new(io::stdin().lock(), io::stdout())),
+    };
+    if user.is_guest() {
+        let mut guest = GuestCollection::new().await?;
+        guest.seed_deck(pool, deck_id).await?;
+        review_deck(&guest.pool, &user, deck_id, &options, frontend.as_mut()).await?;
+        guest.offer_claim(pool).await?;
+    } else {
+        review_deck(pool, &user, deck_id, &options, frontend.as_mut()).await?;
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,137 @@
+use std::process::ExitCode;
+
+use super::{fail, is_unique_violation};
+use crate::hierarchy::normalize_name;
+use crate::output::{print_rows, OutputFormat};
+use crate::queries::{
+    create_tag, delete_tag, list_cards_for_tag, list_tags, query_tag_id, update_tag,
+};
+use clap::{ArgGroup, Subcommand};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+/// Tags nest with `::`, so `lang::spanish` is a tag under `lang`.
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// lists all tags with how many cards have them
+    List,
+    /// creates a new tag, along with the tags above it, and prints its id
+    Create {
+        /// the name of the tag
+        #[arg(short, long)]
+        name: String,
+
+        /// the description of the tag
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// renames a tag and the tags under it or changes its description
+    #[command(group(ArgGroup::new("changes").required(true).multiple(true).args(["name", "description"])))]
+    Update {
+        /// the id or name of the tag
+        tag: String,
+
+        /// the new name of the tag
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// the new description of the tag
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// deletes a tag and the tags under it and takes them off every card
+    Delete {
+        /// the id or name of the tag
+        tag: String,
+    },
+    /// lists the cards with a tag or one of the tags under it
+    Cards {
+        /// the id or name of the tag
+        tag: String,
+    },
+}
+
+/// Runs a tag command in a transaction that is only committed when it succeeds.
+pub async fn run(pool: &SqlitePool, command: TagCommands, format: OutputFormat) -> ExitCode {
+    let result = async {
+        let mut tx = pool.begin().await?;
+        let code = execute(&mut tx, command, format).await?;
+        if code == ExitCode::SUCCESS {
+            tx.commit().await?;
+        }
+        Ok::<_, sqlx::Error>(code)
+    };
+
+    match result.await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn execute(
+    tx: &mut Transaction<'_, Sqlite>,
+    command: TagCommands,
+    format: OutputFormat,
+) -> Result<ExitCode, sqlx::Error> {
+    match command {
+        TagCommands::List => print_rows(&list_tags(tx).await?, format),
+        TagCommands::Create { name, description } => {
+            let Some(name) = normalize_name(&name) else {
+                return Ok(fail(
+                    "the name of a tag cannot be empty or have empty levels",
+                ));
+            };
+            match create_tag(tx, name.clone(), description).await {
+                Ok(id) => println!("{}", id),
+                Err(e) if is_unique_violation(&e) => return Ok(name_taken(&name)),
+                Err(e) => return Err(e),
+            }
+        }
+        TagCommands::Update {
+            tag,
+            name,
+            description,
+        } => {
+            let Some(id) = query_tag_id(tx, &tag).await? else {
+                return Ok(no_tag(&tag));
+            };
+            let name = match name.as_deref().map(normalize_name) {
+                Some(None) => {
+                    return Ok(fail(
+                        "the name of a tag cannot be empty or have empty levels",
+                    ))
+                }
+                name => name.flatten(),
+            };
+            match update_tag(tx, id, name.clone(), description).await {
+                Ok(_) => println!("Updated tag with id: {}", id),
+                Err(e) if is_unique_violation(&e) => {
+                    return Ok(name_taken(name.as_deref().unwrap_or_default()))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        TagCommands::Delete { tag } => {
+            let Some(id) = query_tag_id(tx, &tag).await? else {
+                return Ok(no_tag(&tag));
+            };
+            delete_tag(tx, id).await?;
+            println!("Deleted tag with id: {}", id);
+        }
+        TagCommands::Cards { tag } => {
+            let Some(id) = query_tag_id(tx, &tag).await? else {
+                return Ok(no_tag(&tag));
+            };
+            print_rows(&list_cards_for_tag(tx, id).await?, format);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn no_tag(tag: &str) -> ExitCode {
+    fail(format!("no tag with id or name {}", tag))
+}
+
+fn name_taken(name: &str) -> ExitCode {
+    fail(format!("a tag named {} already exists", name))
+}
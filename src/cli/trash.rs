@@ -0,0 +1,96 @@
+use std::process::ExitCode;
+
+use super::fail;
+use crate::output::{print_rows, OutputFormat};
+use crate::queries::{list_trash, purge_expired_trash, purge_trash, restore_card, restore_deck};
+use clap::{ArgGroup, Subcommand};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+/// The variable that sets how many days deleted cards and decks are kept in the trash.
+pub const RETENTION_VARIABLE: &str = "ANKIRS_TRASH_RETENTION_DAYS";
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Trash commands see the shared decks and the cards in no private deck, like the deck commands.
+#[derive(Subcommand)]
+pub enum TrashCommands {
+    /// lists the deleted cards and decks
+    List,
+    /// takes a card or a deck out of the trash, a deck along with the cards deleted with it
+    #[command(group(ArgGroup::new("item").required(true).args(["card", "deck"])))]
+    Restore {
+        /// the id of the card
+        #[arg(short, long)]
+        card: Option<i64>,
+
+        /// the id of the deck
+        #[arg(short, long)]
+        deck: Option<i64>,
+    },
+    /// deletes the listed cards and decks for good
+    Purge,
+}
+
+/// Runs a trash command in a transaction that is only committed when it succeeds.
+pub async fn run(pool: &SqlitePool, command: TrashCommands, format: OutputFormat) -> ExitCode {
+    let result = async {
+        let mut tx = pool.begin().await?;
+        let code = execute(&mut tx, command, format).await?;
+        if code == ExitCode::SUCCESS {
+            tx.commit().await?;
+        }
+        Ok::<_, sqlx::Error>(code)
+    };
+
+    match result.await {
+        Ok(code) => code,
+        Err(e) => fail(e),
+    }
+}
+
+async fn execute(
+    tx: &mut Transaction<'_, Sqlite>,
+    command: TrashCommands,
+    format: OutputFormat,
+) -> Result<ExitCode, sqlx::Error> {
+    match command {
+        TrashCommands::List => print_rows(&list_trash(tx, None).await?, format),
+        TrashCommands::Restore { card: Some(id), .. } => {
+            if !restore_card(tx, id).await? {
+                return Ok(fail(format!("no card with id {} in the trash", id)));
+            }
+            println!("Restored card with id: {}", id);
+        }
+        TrashCommands::Restore { deck, .. } => {
+            let id = deck.expect("clap requires a card or a deck");
+            if !restore_deck(tx, None, id).await? {
+                return Ok(fail(format!("no deck with id {} in the trash", id)));
+            }
+            println!("Restored deck with id: {}", id);
+        }
+        TrashCommands::Purge => {
+            let count = purge_trash(tx, None).await?;
+            println!("Deleted {} card(s) and deck(s) for good", count);
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Deletes what has been in the trash for longer than the retention, which is read from
+/// `ANKIRS_TRASH_RETENTION_DAYS` and defaults to 30 days.
+pub async fn purge_expired(pool: &SqlitePool) -> Result<(), String> {
+    let retention_days = match dotenv::var(RETENTION_VARIABLE) {
+        Ok(days) => match days.trim().parse::<i64>() {
+            Ok(days) if days >= 0 => days,
+            _ => return Err(format!("{} must be a number of days", RETENTION_VARIABLE)),
+        },
+        Err(_) => DEFAULT_RETENTION_DAYS,
+    };
+
+    let purge = async {
+        let mut tx = pool.begin().await?;
+        purge_expired_trash(&mut tx, retention_days).await?;
+        tx.commit().await
+    };
+    purge.await.map_err(|e: sqlx::Error| e.to_string())
+}
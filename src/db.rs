@@ -0,0 +1,59 @@
+use std::str::FromStr;
+
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+
+/// The migrations in `migrations/`, embedded in the binary.
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// The database used when `DATABASE_URL` is not set.
+pub const DEFAULT_DATABASE_URL: &str = "sqlite:anki.db";
+
+/// Opens the database at `url`, creating the file if it does not exist yet.
+///
+/// The migrations are not run, see `MIGRATOR`.
+pub async fn connect(url: &str) -> Result<SqlitePool, sqlx::Error> {
+    // deletes rely on the foreign keys to take the rows that point at them along
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .foreign_keys(true);
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::migrate::Migrate;
+
+    #[tokio::test]
+    async fn test_migrations_roll_back() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        MIGRATOR.run(&pool).await.unwrap();
+        MIGRATOR.undo(&pool, 0).await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        assert!(conn.list_applied_migrations().await.unwrap().is_empty());
+        let tables: Vec<String> = sqlx::query_scalar(
+            r"
+            SELECT name FROM sqlite_master
+            WHERE type = 'table' AND name != '_sqlx_migrations' AND name NOT LIKE 'sqlite\_%' ESCAPE '\'
+            ",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(tables, Vec::<String>::new());
+        drop(conn);
+
+        // and the schema can be built again from there
+        MIGRATOR.run(&pool).await.unwrap();
+    }
+}
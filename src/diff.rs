@@ -0,0 +1,162 @@
+use colored::*;
+
+/// One step of the alignment between a typed answer and the expected back of a card.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Edit {
+    /// The character was typed correctly.
+    Same(char),
+    /// A different character was typed in place of the expected one.
+    Wrong { given: char, expected: char },
+    /// The character was typed but is not in the expected answer.
+    Extra(char),
+    /// The expected character was not typed.
+    Missing(char),
+}
+
+/// Aligns `given` with `expected` character by character, with as few edits as possible.
+pub fn diff(given: &str, expected: &str) -> Vec<Edit> {
+    let given: Vec<char> = given.chars().collect();
+    let expected: Vec<char> = expected.chars().collect();
+    let (n, m) = (given.len(), expected.len());
+
+    // distances[i][j] is the edit distance between given[i..] and expected[j..]
+    let mut distances = vec![vec![0; m + 1]; n + 1];
+    for i in (0..=n).rev() {
+        for j in (0..=m).rev() {
+            distances[i][j] = if i == n {
+                m - j
+            } else if j == m {
+                n - i
+            } else {
+                let cost = usize::from(given[i] != expected[j]);
+                (distances[i + 1][j + 1] + cost)
+                    .min(distances[i + 1][j] + 1)
+                    .min(distances[i][j + 1] + 1)
+            };
+        }
+    }
+
+    let mut edits = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && given[i] == expected[j] && distances[i][j] == distances[i + 1][j + 1] {
+            edits.push(Edit::Same(given[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if i < n && j < m && distances[i][j] == distances[i + 1][j + 1] + 1 {
+            edits.push(Edit::Wrong {
+                given: given[i],
+                expected: expected[j],
+            });
+            (i, j) = (i + 1, j + 1);
+        } else if i < n && distances[i][j] == distances[i + 1][j] + 1 {
+            edits.push(Edit::Extra(given[i]));
+            i += 1;
+        } else {
+            edits.push(Edit::Missing(expected[j]));
+            j += 1;
+        }
+    }
+
+    edits
+}
+
+/// Renders a diff as two aligned, coloured lines: what was typed and what was expected.
+///
+/// Correct characters are green, wrong and extra ones red, and missing ones are shown as
+/// a red `-` in the typed line and in yellow in the expected line.
+pub fn render(edits: &[Edit]) -> (String, String) {
+    let mut given = String::new();
+    let mut expected = String::new();
+
+    for edit in edits {
+        let (g, e) = match *edit {
+            Edit::Same(c) => (c.to_string().green(), c.to_string().green()),
+            Edit::Wrong {
+                given: g,
+                expected: e,
+            } => (g.to_string().red().bold(), e.to_string().yellow().bold()),
+            Edit::Extra(c) => (c.to_string().red().strikethrough(), "-".dimmed()),
+            Edit::Missing(c) => ("-".red().bold(), c.to_string().yellow().underline()),
+        };
+        given.push_str(&g.to_string());
+        expected.push_str(&e.to_string());
+    }
+
+    (given, expected)
+}
+
+/// Prints the typed answer above the expected one with the differences highlighted.
+pub fn print_diff(given: &str, expected: &str) {
+    let (given, expected) = render(&diff(given, expected));
+    println!("  typed:    {}", given);
+    println!("  expected: {}", expected);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_identical() {
+        assert_eq!(diff("ab", "ab"), vec![Edit::Same('a'), Edit::Same('b')]);
+        assert_eq!(diff("", ""), vec![]);
+    }
+
+    #[test]
+    fn test_diff_edits() {
+        assert_eq!(
+            diff("hause", "house"),
+            vec![
+                Edit::Same('h'),
+                Edit::Wrong {
+                    given: 'a',
+                    expected: 'o'
+                },
+                Edit::Same('u'),
+                Edit::Same('s'),
+                Edit::Same('e'),
+            ]
+        );
+        assert_eq!(
+            diff("helo", "hello"),
+            vec![
+                Edit::Same('h'),
+                Edit::Same('e'),
+                Edit::Same('l'),
+                Edit::Missing('l'),
+                Edit::Same('o'),
+            ]
+        );
+        assert_eq!(
+            diff("caat", "cat"),
+            vec![
+                Edit::Same('c'),
+                Edit::Same('a'),
+                Edit::Extra('a'),
+                Edit::Same('t'),
+            ]
+        );
+    }
+
+    /// Drops the colour codes, which depend on the terminal the tests run in.
+    fn strip_colours(text: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn test_render_is_aligned() {
+        let (given, expected) = render(&diff("caat", "cot"));
+        assert_eq!(strip_colours(&given), "caat");
+        assert_eq!(strip_colours(&expected), "co-t");
+        assert_eq!(strip_colours("\x1b[1;31mco\x1b[0m-t"), "co-t");
+    }
+}
@@ -0,0 +1,332 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::scheduler::Rating;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Rewrites an answer before it is compared, so that irrelevant differences are ignored.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Normalizer {
+    /// Ignores upper and lower case.
+    CaseFold,
+    /// Ignores accents and other diacritics, so that "café" matches "cafe".
+    Accents,
+    /// Ignores punctuation and symbols.
+    Punctuation,
+}
+
+impl Normalizer {
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Normalizer::CaseFold => text.to_lowercase(),
+            Normalizer::Accents => text
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .nfc()
+                .collect(),
+            Normalizer::Punctuation => text
+                .chars()
+                .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+                .collect(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Normalizer::CaseFold => "casefold",
+            Normalizer::Accents => "accents",
+            Normalizer::Punctuation => "punctuation",
+        }
+    }
+}
+
+/// Scores how similar an answer is to the back of a card, from 0 to 1.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparator {
+    /// Only identical answers score.
+    Exact,
+    /// One minus the edit distance relative to the length of the longest answer.
+    Levenshtein,
+    /// Like `Levenshtein`, but swapping two adjacent characters is a single edit.
+    Damerau,
+    /// The share of words both answers have in common, in any order.
+    TokenSet,
+    /// Compares the answers as numbers, which match when within `tolerance` of each other.
+    Numeric { tolerance: f64 },
+}
+
+impl Comparator {
+    pub fn similarity(self, given: &str, expected: &str) -> f64 {
+        match self {
+            Comparator::Exact => {
+                if given == expected {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Comparator::Levenshtein => edit_similarity(given, expected, false),
+            Comparator::Damerau => edit_similarity(given, expected, true),
+            Comparator::TokenSet => {
+                let given: BTreeSet<&str> = given.split_whitespace().collect();
+                let expected: BTreeSet<&str> = expected.split_whitespace().collect();
+                let union = given.union(&expected).count();
+                if union == 0 {
+                    return 1.0;
+                }
+                given.intersection(&expected).count() as f64 / union as f64
+            }
+            Comparator::Numeric { tolerance } => {
+                match (given.trim().parse::<f64>(), expected.trim().parse::<f64>()) {
+                    (Ok(given), Ok(expected)) => {
+                        let difference = (given - expected).abs();
+                        if difference <= tolerance {
+                            1.0
+                        } else {
+                            tolerance / difference
+                        }
+                    }
+                    _ => Comparator::Exact.similarity(given, expected),
+                }
+            }
+        }
+    }
+}
+
+/// The number of single character edits needed to turn `a` into `b`.
+///
+/// With `transpositions`, swapping two adjacent characters counts as a single edit
+/// (the optimal string alignment variant of the Damerau-Levenshtein distance).
+pub fn edit_distance(a: &str, b: &str, transpositions: bool) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+fn edit_similarity(given: &str, expected: &str, transpositions: bool) -> f64 {
+    let longest = given.chars().count().max(expected.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(given, expected, transpositions) as f64 / longest as f64
+}
+
+/// Grades typed answers: normalizes both sides, then scores them with a comparator.
+///
+/// A perfect score is `Good`, a score of at least `threshold` is a near miss graded `Hard`
+/// and anything lower is `Again`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Grader {
+    pub normalizers: Vec<Normalizer>,
+    pub comparator: Comparator,
+    pub threshold: f64,
+}
+
+impl Default for Grader {
+    fn default() -> Self {
+        Self {
+            normalizers: vec![],
+            comparator: Comparator::Exact,
+            threshold: 1.0,
+        }
+    }
+}
+
+impl Grader {
+    fn normalize(&self, text: &str) -> String {
+        let text = self
+            .normalizers
+            .iter()
+            .fold(text.to_string(), |text, normalizer| normalizer.apply(&text));
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// How close the given answer is to the expected one, from 0 to 1.
+    pub fn score(&self, given: &str, expected: &str) -> f64 {
+        self.comparator
+            .similarity(&self.normalize(given), &self.normalize(expected))
+    }
+
+    pub fn rating(&self, score: f64) -> Rating {
+        if score >= 1.0 {
+            Rating::Good
+        } else if score >= self.threshold {
+            Rating::Hard
+        } else {
+            Rating::Again
+        }
+    }
+}
+
+/// Parses a grader from a comma separated list of normalizers followed by a comparator,
+/// such as `casefold,accents,levenshtein=0.8`.
+///
+/// The comparator takes an optional near miss threshold, except `numeric` which takes the
+/// tolerance within which two numbers match.
+impl FromStr for Grader {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut grader = Grader::default();
+        let mut comparator_given = false;
+
+        for item in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            let (name, value) = match item.split_once('=') {
+                Some((name, value)) => {
+                    let value = value
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| format!("invalid value in \"{}\"", item))?;
+                    (name.trim(), Some(value))
+                }
+                None => (item, None),
+            };
+
+            let normalizer = match name {
+                "casefold" => Some(Normalizer::CaseFold),
+                "accents" => Some(Normalizer::Accents),
+                "punctuation" => Some(Normalizer::Punctuation),
+                _ => None,
+            };
+            if let Some(normalizer) = normalizer {
+                if value.is_some() {
+                    return Err(format!("{} does not take a value", name));
+                }
+                grader.normalizers.push(normalizer);
+                continue;
+            }
+
+            if comparator_given {
+                return Err(format!(
+                    "only one comparator can be used, found \"{}\"",
+                    name
+                ));
+            }
+            comparator_given = true;
+            (grader.comparator, grader.threshold) = match name {
+                "exact" => (Comparator::Exact, 1.0),
+                "levenshtein" => (Comparator::Levenshtein, value.unwrap_or(0.8)),
+                "damerau" => (Comparator::Damerau, value.unwrap_or(0.8)),
+                "tokens" => (Comparator::TokenSet, value.unwrap_or(0.5)),
+                "numeric" => (
+                    Comparator::Numeric {
+                        tolerance: value.unwrap_or(0.0),
+                    },
+                    0.5,
+                ),
+                _ => return Err(format!("unknown grading function \"{}\"", name)),
+            };
+        }
+
+        Ok(grader)
+    }
+}
+
+impl fmt::Display for Grader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items: Vec<String> = self
+            .normalizers
+            .iter()
+            .map(|normalizer| normalizer.name().to_string())
+            .collect();
+        items.push(match self.comparator {
+            Comparator::Exact => "exact".to_string(),
+            Comparator::Levenshtein => format!("levenshtein={}", self.threshold),
+            Comparator::Damerau => format!("damerau={}", self.threshold),
+            Comparator::TokenSet => format!("tokens={}", self.threshold),
+            Comparator::Numeric { tolerance } => format!("numeric={}", tolerance),
+        });
+        write!(f, "{}", items.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizers() {
+        assert_eq!(Normalizer::CaseFold.apply("HeLLo"), "hello");
+        assert_eq!(Normalizer::Accents.apply("Café Ñandú"), "Cafe Nandu");
+        assert_eq!(Normalizer::Punctuation.apply("¡Hola, mundo!"), "Hola mundo");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting", false), 3);
+        assert_eq!(edit_distance("", "abc", false), 3);
+        assert_eq!(edit_distance("ab", "ba", false), 2);
+        assert_eq!(edit_distance("ab", "ba", true), 1);
+    }
+
+    #[test]
+    fn test_comparators() {
+        assert_eq!(Comparator::Exact.similarity("a", "b"), 0.0);
+        assert_eq!(Comparator::Levenshtein.similarity("abcd", "abce"), 0.75);
+        assert_eq!(Comparator::TokenSet.similarity("b a", "a b"), 1.0);
+        assert_eq!(Comparator::TokenSet.similarity("a b c", "a b d"), 0.5);
+
+        let numeric = Comparator::Numeric { tolerance: 0.01 };
+        assert_eq!(numeric.similarity("3.14", "3.14159"), 1.0);
+        assert!((numeric.similarity("3.16", "3.14") - 0.5).abs() < 1e-9);
+        assert_eq!(numeric.similarity("pi", "pi"), 1.0);
+    }
+
+    #[test]
+    fn test_grader_ratings() {
+        let grader: Grader = "casefold,accents,punctuation,damerau=0.8".parse().unwrap();
+
+        assert_eq!(
+            grader.rating(grader.score("Él está.", "el esta")),
+            Rating::Good
+        );
+        assert_eq!(
+            grader.rating(grader.score("recieve", "receive")),
+            Rating::Hard
+        );
+        assert_eq!(
+            grader.rating(grader.score("take", "receive")),
+            Rating::Again
+        );
+
+        let exact = Grader::default();
+        assert_eq!(exact.rating(exact.score(" back ", "back")), Rating::Good);
+        assert_eq!(exact.rating(exact.score("Back", "back")), Rating::Again);
+    }
+
+    #[test]
+    fn test_grader_spec_round_trip() {
+        for spec in ["exact", "casefold,levenshtein=0.8", "accents,numeric=0.5"] {
+            assert_eq!(spec.parse::<Grader>().unwrap().to_string(), spec);
+        }
+        assert!("soundex".parse::<Grader>().is_err());
+        assert!("exact,tokens".parse::<Grader>().is_err());
+        assert!("casefold=1".parse::<Grader>().is_err());
+    }
+}
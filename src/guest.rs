@@ -0,0 +1,727 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::auth::prompt;
+use crate::db::MIGRATOR;
+use crate::models::User;
+use crate::output::{print_rows, OutputFormat};
+use crate::queries::{
+    create_tag, create_user, list_decks, purge_trash, query_deck_visible, query_tag_id,
+    query_user_credentials, rebuild_filtered_deck, tag_card,
+};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Acquire, Sqlite, SqlitePool, Transaction};
+
+/// A throwaway collection that a guest studies in.
+///
+/// It lives in an in-memory database, so nothing a guest does ends up in the database of
+/// the registered users unless the guest claims it into an account.
+pub struct GuestCollection {
+    pub pool: SqlitePool,
+    /// The shared deck copied from the main database, which keeps its id here.
+    seeded_deck: Option<i64>,
+    /// The cards copied with the seeded deck. Each of them was put straight in the seeded
+    /// deck, even when it is in a subdeck in the main database, so these links are never
+    /// claimed.
+    seeded_cards: HashSet<i64>,
+}
+
+impl GuestCollection {
+    /// Creates an empty collection by running the migrations on an in-memory database.
+    pub async fn new() -> Result<Self, sqlx::Error> {
+        // every connection to an in-memory database opens a database of its own, so the pool
+        // keeps a single connection alive for as long as it exists
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await?;
+        MIGRATOR.run(&pool).await?;
+
+        Ok(Self {
+            pool,
+            seeded_deck: None,
+            seeded_cards: HashSet::new(),
+        })
+    }
+
+    /// Copies a shared deck and its cards from the main database, keeping their ids.
+    ///
+    /// The tags are copied along with their ids too, so that a review can be narrowed to a tag
+    /// the same way in both databases.
+    pub async fn seed_deck(&mut self, main: &SqlitePool, deck_id: i64) -> Result<(), sqlx::Error> {
+        let mut source = main.begin().await?;
+        let deck = sqlx::query!(
+            r#"
+            SELECT id AS "id!", name, description, scheduler, review_mode, grading
+            FROM deck
+            WHERE id = ? AND owner_id IS NULL AND deleted_at IS NULL
+            "#,
+            deck_id
+        )
+        .fetch_one(source.acquire().await?)
+        .await?;
+        // the cards of the shared subdecks go straight into the deck, which is all a review
+        // of it needs
+        let cards = sqlx::query!(
+            r#"
+            SELECT DISTINCT card.id AS "id!", card.front, card.back
+            FROM card
+            JOIN deck_card ON deck_card.card_id = card.id
+            JOIN deck_tree ON deck_tree.deck_id = deck_card.deck_id
+            JOIN deck ON deck.id = deck_card.deck_id
+            WHERE deck_tree.ancestor_id = ?1
+            AND (deck.id = ?1 OR deck.owner_id IS NULL AND deck.deleted_at IS NULL)
+            AND card.deleted_at IS NULL
+            AND NOT card.suspended
+            "#,
+            deck_id
+        )
+        .fetch_all(source.acquire().await?)
+        .await?;
+        let tags = sqlx::query!(r#"SELECT id AS "id!", name, description FROM tag"#)
+            .fetch_all(source.acquire().await?)
+            .await?;
+        let card_tags = sqlx::query!("SELECT card_id, tag_id FROM card_tag")
+            .fetch_all(source.acquire().await?)
+            .await?;
+        source.commit().await?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO deck (id, name, description, scheduler, review_mode, grading)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+            deck.id,
+            deck.name,
+            deck.description,
+            deck.scheduler,
+            deck.review_mode,
+            deck.grading
+        )
+        .execute(tx.acquire().await?)
+        .await?;
+        for card in cards {
+            sqlx::query!(
+                "INSERT INTO card (id, front, back) VALUES (?, ?, ?)",
+                card.id,
+                card.front,
+                card.back
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+            sqlx::query!(
+                "INSERT INTO card_deck (card_id, deck_id) VALUES (?, ?)",
+                card.id,
+                deck.id
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+            self.seeded_cards.insert(card.id);
+        }
+        for tag in tags {
+            sqlx::query!(
+                "INSERT INTO tag (id, name, description) VALUES (?, ?, ?)",
+                tag.id,
+                tag.name,
+                tag.description
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+        }
+        for card_tag in card_tags {
+            if self.seeded_cards.contains(&card_tag.card_id) {
+                sqlx::query!(
+                    "INSERT INTO card_tag (card_id, tag_id) VALUES (?, ?)",
+                    card_tag.card_id,
+                    card_tag.tag_id
+                )
+                .execute(tx.acquire().await?)
+                .await?;
+            }
+        }
+        tx.commit().await?;
+
+        self.seeded_deck = Some(deck.id);
+        Ok(())
+    }
+
+    /// Copies everything done as a guest into a new account of the main database, returning
+    /// `None` when the username is already taken.
+    ///
+    /// The seeded deck and its cards already exist there, anything else the guest created
+    /// is added, with decks private to the new account. The shared deck is left as it is, so
+    /// the cards the guest added to it go into a private deck named after it. A deck whose
+    /// name is taken, even by a deck in the trash, gets a number after its name.
+    ///
+    /// Suspending a seeded card is not kept, as it would suspend the card for everyone.
+    /// Filtered decks are rebuilt once everything is copied, borrowing the cards matching
+    /// their search for the new account.
+    pub async fn claim(
+        &self,
+        main: &SqlitePool,
+        username: String,
+        password: String,
+    ) -> Result<Option<User>, sqlx::Error> {
+        let mut tx = main.begin().await?;
+        if query_user_credentials(&mut tx, &username).await?.is_some() {
+            return Ok(None);
+        }
+        let user_id = create_user(&mut tx, username.clone(), password).await?;
+        let mut guest = self.pool.begin().await?;
+        // what the guest deleted is not worth keeping
+        purge_trash(&mut guest, None).await?;
+
+        let card_decks = sqlx::query!("SELECT card_id, deck_id FROM card_deck")
+            .fetch_all(guest.acquire().await?)
+            .await?;
+        let added_to_seeded_deck = card_decks.iter().any(|card_deck| {
+            self.seeded_deck == Some(card_deck.deck_id)
+                && !self.seeded_cards.contains(&card_deck.card_id)
+        });
+
+        let mut decks = HashMap::new();
+        let mut seeded_deck_copy = None;
+        let mut filtered_decks = vec![];
+        let guest_decks = sqlx::query!(
+            r#"
+            SELECT
+                id AS "id!", name, description, scheduler, review_mode, grading, search,
+                search_limit
+            FROM deck
+            "#
+        )
+        .fetch_all(guest.acquire().await?)
+        .await?;
+        for deck in guest_decks {
+            let seeded = self.seeded_deck == Some(deck.id);
+            if seeded {
+                decks.insert(deck.id, deck.id);
+                if !added_to_seeded_deck {
+                    continue;
+                }
+            }
+            let name = unused_deck_name(&mut tx, &deck.name).await?;
+            if seeded {
+                println!(
+                    "The cards you added to {} were saved in {}",
+                    deck.name, name
+                );
+            } else if name != deck.name {
+                println!("Your deck {} was saved as {}", deck.name, name);
+            }
+            let id = sqlx::query!(
+                r#"
+                INSERT INTO deck (
+                    name, description, scheduler, review_mode, grading, search, search_limit,
+                    owner_id
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                RETURNING id
+                "#,
+                name,
+                deck.description,
+                deck.scheduler,
+                deck.review_mode,
+                deck.grading,
+                deck.search,
+                deck.search_limit,
+                user_id
+            )
+            .fetch_one(tx.acquire().await?)
+            .await?
+            .id;
+            if deck.search.is_some() {
+                filtered_decks.push(id);
+            }
+            if seeded {
+                seeded_deck_copy = Some(id);
+            } else {
+                decks.insert(deck.id, id);
+            }
+        }
+
+        let mut cards = HashMap::new();
+        let guest_cards = sqlx::query!(r#"SELECT id AS "id!", front, back, suspended FROM card"#)
+            .fetch_all(guest.acquire().await?)
+            .await?;
+        for card in guest_cards {
+            let id = if self.seeded_cards.contains(&card.id) {
+                card.id
+            } else {
+                sqlx::query!(
+                    "INSERT INTO card (front, back, suspended) VALUES (?, ?, ?) RETURNING id",
+                    card.front,
+                    card.back,
+                    card.suspended
+                )
+                .fetch_one(tx.acquire().await?)
+                .await?
+                .id
+            };
+            cards.insert(card.id, id);
+        }
+
+        for card_deck in card_decks {
+            let deck_id = if self.seeded_deck != Some(card_deck.deck_id) {
+                decks[&card_deck.deck_id]
+            } else if self.seeded_cards.contains(&card_deck.card_id) {
+                continue;
+            } else {
+                // there is a copy whenever the guest added a card to the seeded deck
+                seeded_deck_copy.unwrap()
+            };
+            sqlx::query!(
+                "INSERT OR IGNORE INTO card_deck (card_id, deck_id) VALUES (?, ?)",
+                cards[&card_deck.card_id],
+                deck_id
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+        }
+
+        // tags are shared, so the ones the guest used are looked up by name
+        let card_tags = sqlx::query!(
+            r#"
+            SELECT card_tag.card_id, tag.name, tag.description
+            FROM card_tag
+            JOIN tag ON tag.id = card_tag.tag_id
+            "#
+        )
+        .fetch_all(guest.acquire().await?)
+        .await?;
+        for card_tag in card_tags {
+            let tag_id = match query_tag_id(&mut tx, &card_tag.name).await? {
+                Some(tag_id) => tag_id,
+                None => create_tag(&mut tx, card_tag.name, card_tag.description).await?,
+            };
+            tag_card(&mut tx, cards[&card_tag.card_id], tag_id).await?;
+        }
+
+        let schedules = sqlx::query!(
+            r#"
+            SELECT
+                card_id, ease_factor, interval_days, repetitions, stability, difficulty,
+                CAST(due_at AS TEXT) AS "due_at!: String",
+                CAST(last_reviewed_at AS TEXT) AS "last_reviewed_at: String"
+            FROM card_schedule
+            "#
+        )
+        .fetch_all(guest.acquire().await?)
+        .await?;
+        for schedule in schedules {
+            sqlx::query!(
+                r#"
+                INSERT INTO card_schedule (
+                    user_id, card_id, ease_factor, interval_days, repetitions, stability,
+                    difficulty, due_at, last_reviewed_at
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                user_id,
+                cards[&schedule.card_id],
+                schedule.ease_factor,
+                schedule.interval_days,
+                schedule.repetitions,
+                schedule.stability,
+                schedule.difficulty,
+                schedule.due_at,
+                schedule.last_reviewed_at
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+        }
+
+        let mut sessions = HashMap::new();
+        let guest_sessions = sqlx::query!(
+            r#"
+            SELECT
+                id AS "id!",
+                deck_id,
+                CAST(start_time AS TEXT) AS "start_time!: String",
+                CAST(end_time AS TEXT) AS "end_time: String",
+                review_mode
+            FROM session
+            "#
+        )
+        .fetch_all(guest.acquire().await?)
+        .await?;
+        for session in guest_sessions {
+            let deck_id = session.deck_id.map(|deck_id| decks[&deck_id]);
+            let id = sqlx::query!(
+                r#"
+                INSERT INTO session (user_id, deck_id, start_time, end_time, review_mode)
+                VALUES (?, ?, ?, ?, ?)
+                RETURNING id AS "id!"
+                "#,
+                user_id,
+                deck_id,
+                session.start_time,
+                session.end_time,
+                session.review_mode
+            )
+            .fetch_one(tx.acquire().await?)
+            .await?
+            .id;
+            sessions.insert(session.id, id);
+        }
+        let session_cards = sqlx::query!("SELECT session_id, card_id FROM session_card")
+            .fetch_all(guest.acquire().await?)
+            .await?;
+        for session_card in session_cards {
+            sqlx::query!(
+                "INSERT INTO session_card (session_id, card_id) VALUES (?, ?)",
+                sessions[&session_card.session_id],
+                cards[&session_card.card_id]
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+        }
+
+        let answers = sqlx::query!(
+            r#"
+            SELECT
+                card_id, deck_id, session_id, answer, correct_answer, grade, elapsed_ms,
+                CAST(time AS TEXT) AS "time!: String"
+            FROM answer
+            "#
+        )
+        .fetch_all(guest.acquire().await?)
+        .await?;
+        for answer in answers {
+            let deck_id = answer.deck_id.map(|deck_id| decks[&deck_id]);
+            sqlx::query!(
+                r#"
+                INSERT INTO answer (
+                    user_id, card_id, deck_id, session_id, answer, correct_answer, grade,
+                    elapsed_ms, time
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+                user_id,
+                cards[&answer.card_id],
+                deck_id,
+                sessions[&answer.session_id],
+                answer.answer,
+                answer.correct_answer,
+                answer.grade,
+                answer.elapsed_ms,
+                answer.time
+            )
+            .execute(tx.acquire().await?)
+            .await?;
+        }
+
+        // the borrowed cards depend on the schedules and answers, so they are picked again
+        for id in filtered_decks {
+            rebuild_filtered_deck(&mut tx, id).await?;
+        }
+
+        guest.commit().await?;
+        tx.commit().await?;
+
+        Ok(Some(User::authenticated(user_id, username)))
+    }
+
+    /// Asks the guest whether to keep their progress, and claims it into a new account if so.
+    pub async fn offer_claim(&self, main: &SqlitePool) -> Result<(), sqlx::Error> {
+        let answer = prompt("Would you like to keep your progress in a new account? (y/n)")?;
+        if !answer.eq_ignore_ascii_case("y") {
+            println!("Your guest progress was discarded");
+            return Ok(());
+        }
+
+        loop {
+            let username = prompt("Please choose a username:")?;
+            let password = prompt("Please choose a password:")?;
+            if username.is_empty() || password.is_empty() {
+                println!("The username and password cannot be empty");
+                continue;
+            }
+
+            match self.claim(main, username, password).await? {
+                Some(user) => {
+                    println!("Your progress was saved as {:?}", user);
+                    return Ok(());
+                }
+                None => println!("That username is already taken"),
+            }
+        }
+    }
+}
+
+/// Finds a name for a deck that no deck of the main database has, adding a number to it
+/// when it is taken.
+async fn unused_deck_name(
+    tx: &mut Transaction<'_, Sqlite>,
+    name: &str,
+) -> Result<String, sqlx::Error> {
+    let mut candidate = name.to_string();
+    for number in 2.. {
+        let taken = sqlx::query!("SELECT id FROM deck WHERE name = ?", candidate)
+            .fetch_optional(tx.acquire().await?)
+            .await?
+            .is_some();
+        if !taken {
+            break;
+        }
+        candidate = format!("{} ({})", name, number);
+    }
+
+    Ok(candidate)
+}
+
+/// Creates the collection of a guest, offering to copy one of the shared decks into it.
+pub async fn start_guest_collection(main: &SqlitePool) -> Result<GuestCollection, sqlx::Error> {
+    let mut collection = GuestCollection::new().await?;
+
+    // no transaction is kept open while waiting for the guest to type
+    let mut tx = main.begin().await?;
+    let decks = list_decks(&mut tx, None).await?;
+    tx.commit().await?;
+    println!("Guests study in a collection of their own that is discarded when they quit");
+    print_rows(&decks, OutputFormat::Table);
+    let deck_id = loop {
+        let input = prompt("Enter the id of a deck to copy, or nothing to start empty:")?;
+        if input.is_empty() {
+            break None;
+        }
+        let Ok(id) = input.parse::<i64>() else {
+            println!("Deck does not exist");
+            continue;
+        };
+        let mut tx = main.begin().await?;
+        let visible = query_deck_visible(&mut tx, None, id).await?;
+        tx.commit().await?;
+        if visible {
+            break Some(id);
+        }
+        println!("Deck does not exist");
+    };
+
+    if let Some(deck_id) = deck_id {
+        collection.seed_deck(main, deck_id).await?;
+    }
+
+    Ok(collection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CardSchedule;
+    use crate::queries::{
+        add_card_to_deck, create_card, create_deck, create_filtered_deck, delete_deck,
+        list_cards_for_deck, list_cards_for_tag, query_card_schedule, query_deck_id,
+        set_card_suspended, update_card_schedule,
+    };
+    use crate::scheduler::{Rating, Scheduler, Sm2};
+
+    #[tokio::test]
+    async fn test_claim_guest_progress() {
+        // an empty collection stands in for the main database
+        let main = GuestCollection::new().await.unwrap().pool;
+        let mut tx = main.begin().await.unwrap();
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "shared deck"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut guest = GuestCollection::new().await.unwrap();
+        guest.seed_deck(&main, deck_id).await.unwrap();
+        let mut tx = guest.pool.begin().await.unwrap();
+        let schedule = Sm2.schedule(&CardSchedule::default(), Rating::Good, 0.0);
+        update_card_schedule(&mut tx, None, card_id, &schedule)
+            .await
+            .unwrap();
+        let guest_card = create_card(&mut tx, "guest".to_string(), "card".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, guest_card, deck_id)
+            .await
+            .unwrap();
+        set_card_suspended(&mut tx, guest_card, true).await.unwrap();
+        create_filtered_deck(
+            &mut tx,
+            "cram".to_string(),
+            None,
+            "front:front".to_string(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        let user = guest
+            .claim(&main, "claimed".to_string(), "pw".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut tx = main.begin().await.unwrap();
+        let (stored, _) = query_card_schedule(&mut tx, user.id(), card_id)
+            .await
+            .unwrap();
+        assert_eq!(stored, schedule);
+        let (stored, _) = query_card_schedule(&mut tx, None, card_id).await.unwrap();
+        assert_eq!(stored, CardSchedule::default());
+
+        // the shared deck is untouched, the card the guest added to it went into a copy
+        let deck_cards = list_cards_for_deck(&mut tx, deck_id).await.unwrap();
+        assert_eq!(deck_cards.len(), 1);
+        let decks = list_decks(&mut tx, user.id()).await.unwrap();
+        let copy = decks
+            .iter()
+            .find(|deck| deck.name == "shared deck (2)")
+            .unwrap();
+        assert!(!query_deck_visible(&mut tx, None, copy.id).await.unwrap());
+        let copy_cards = list_cards_for_deck(&mut tx, copy.id).await.unwrap();
+        assert_eq!(copy_cards.len(), 1);
+        assert_eq!(copy_cards[0].front, "guest");
+        let suspended = sqlx::query!("SELECT suspended FROM card WHERE id = ?", copy_cards[0].id)
+            .fetch_one(tx.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .suspended;
+        assert!(suspended);
+
+        // the filtered deck borrowed the shared card again for the new account
+        let cram = decks.iter().find(|deck| deck.name == "cram").unwrap();
+        let cram_cards = list_cards_for_deck(&mut tx, cram.id).await.unwrap();
+        assert_eq!(cram_cards.len(), 1);
+        assert_eq!(cram_cards[0].id, card_id);
+        tx.commit().await.unwrap();
+
+        assert_eq!(
+            guest
+                .claim(&main, "claimed".to_string(), "pw".to_string())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_seed_deck_copies_tags() {
+        let main = GuestCollection::new().await.unwrap().pool;
+        let mut tx = main.begin().await.unwrap();
+        let deck_id = create_deck(&mut tx, "spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let card_id = create_card(&mut tx, "hablar".to_string(), "to speak".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+        let verbs = create_tag(&mut tx, "grammar::verbs".to_string(), None)
+            .await
+            .unwrap();
+        tag_card(&mut tx, card_id, verbs).await.unwrap();
+        let grammar = query_tag_id(&mut tx, "grammar").await.unwrap().unwrap();
+        tx.commit().await.unwrap();
+
+        let mut guest = GuestCollection::new().await.unwrap();
+        guest.seed_deck(&main, deck_id).await.unwrap();
+
+        // the parent tag has the same id in the guest collection and covers the card
+        let mut tx = guest.pool.begin().await.unwrap();
+        let cards = list_cards_for_tag(&mut tx, grammar).await.unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].tags, vec!["grammar::verbs".to_string()]);
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_claim_renames_taken_decks() {
+        let main = GuestCollection::new().await.unwrap().pool;
+        let mut tx = main.begin().await.unwrap();
+        create_deck(&mut tx, "spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let trashed = create_deck(&mut tx, "spanish (2)".to_string(), None, None)
+            .await
+            .unwrap();
+        delete_deck(&mut tx, None, trashed, false).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let guest = GuestCollection::new().await.unwrap();
+        let mut tx = guest.pool.begin().await.unwrap();
+        let deck_id = create_deck(&mut tx, "spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let card_id = create_card(&mut tx, "hola".to_string(), "hello".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let user = guest
+            .claim(&main, "claimed".to_string(), "pw".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut tx = main.begin().await.unwrap();
+        let decks = list_decks(&mut tx, user.id()).await.unwrap();
+        let claimed = decks
+            .iter()
+            .find(|deck| deck.name == "spanish (3)")
+            .unwrap();
+        assert_eq!(claimed.cards, 1);
+        assert!(!query_deck_visible(&mut tx, None, claimed.id).await.unwrap());
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_claim_keeps_subdecks() {
+        let main = GuestCollection::new().await.unwrap().pool;
+        let mut tx = main.begin().await.unwrap();
+        let spanish = create_deck(&mut tx, "languages::spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let parent = query_deck_id(&mut tx, None, "languages")
+            .await
+            .unwrap()
+            .unwrap();
+        let card_id = create_card(&mut tx, "hola".to_string(), "hello".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, spanish).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let mut guest = GuestCollection::new().await.unwrap();
+        guest.seed_deck(&main, parent).await.unwrap();
+        guest
+            .claim(&main, "claimed".to_string(), "pw".to_string())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // the card was reviewed in the parent deck but stays in the subdeck only
+        let mut tx = main.begin().await.unwrap();
+        let parent_cards = sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!: i64" FROM card_deck WHERE deck_id = ?"#,
+            parent
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .count;
+        assert_eq!(parent_cards, 0);
+        assert_eq!(
+            list_cards_for_deck(&mut tx, spanish).await.unwrap().len(),
+            1
+        );
+        tx.commit().await.unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+//! Tags and decks nest by name, with `::` between the levels as in `lang::spanish::verbs`.
+
+/// Separates the levels of a hierarchical name.
+pub const SEPARATOR: &str = "::";
+
+/// Trims every level of a name, returning `None` when the name or one of its levels is empty.
+pub fn normalize_name(name: &str) -> Option<String> {
+    let levels: Vec<&str> = name.split(SEPARATOR).map(str::trim).collect();
+    if levels.iter().any(|level| level.is_empty()) {
+        return None;
+    }
+    Some(levels.join(SEPARATOR))
+}
+
+/// The names above a name, from the top of the hierarchy down.
+pub fn ancestors(name: &str) -> Vec<&str> {
+    name.match_indices(SEPARATOR)
+        .map(|(index, _)| &name[..index])
+        .collect()
+}
+
+/// How many levels there are above a name.
+pub fn depth(name: &str) -> usize {
+    name.matches(SEPARATOR).count()
+}
+
+/// The last level of a name.
+pub fn leaf(name: &str) -> &str {
+    name.rsplit(SEPARATOR).next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(
+            normalize_name(" lang :: spanish::verbs "),
+            Some("lang::spanish::verbs".to_string())
+        );
+        assert_eq!(normalize_name("spanish"), Some("spanish".to_string()));
+        assert_eq!(normalize_name(""), None);
+        assert_eq!(normalize_name("lang::"), None);
+        assert_eq!(normalize_name("::spanish"), None);
+        assert_eq!(normalize_name("lang:: ::verbs"), None);
+    }
+
+    #[test]
+    fn test_ancestors() {
+        assert_eq!(
+            ancestors("lang::spanish::verbs"),
+            vec!["lang", "lang::spanish"]
+        );
+        assert!(ancestors("spanish").is_empty());
+    }
+
+    #[test]
+    fn test_depth_and_leaf() {
+        assert_eq!(depth("lang::spanish::verbs"), 2);
+        assert_eq!(leaf("lang::spanish::verbs"), "verbs");
+        assert_eq!(depth("spanish"), 0);
+        assert_eq!(leaf("spanish"), "spanish");
+    }
+}
@@ -1,20 +1,44 @@
 mod auth;
 mod app;
+mod cli;
+mod db;
+mod diff;
+mod grading;
+mod guest;
+mod hierarchy;
 mod models;
+mod output;
+mod protocol;
 mod queries;
+mod review;
+mod scheduler;
+mod search;
 
 use app::start_app;
+use cli::card::CardCommands;
+use cli::db::DbCommands;
+use cli::deck::DeckCommands;
+use cli::optimize::OptimizeArgs;
+use cli::review::ReviewArgs;
+use cli::tag::TagCommands;
+use cli::trash::TrashCommands;
+use db::{connect, DEFAULT_DATABASE_URL, MIGRATOR};
+use output::OutputFormat;
 
 use dotenv::dotenv;
-use sqlx::sqlite::SqlitePoolOptions;
 
 use clap::{Parser, Subcommand};
+use std::process::ExitCode;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// how listings are printed
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
 }
 #[derive(Subcommand)]
 enum Commands {
@@ -23,79 +47,73 @@ enum Commands {
     /// oversees all cards in the app
     Card {
         #[command(subcommand)]
-        command: Option<CardCommands>,
+        command: CardCommands,
     },
-}
-
-#[derive(Subcommand)]
-enum CardCommands {
-    /// lists all cards
-    List,
-    /// creates a new card
-    Create {
-        /// the front of the card
-        #[arg(short, long)]
-        front: String,
-
-        /// the back of the card
-        #[arg(short, long)]
-        back: String,
+    /// oversees all decks in the app
+    Deck {
+        #[command(subcommand)]
+        command: DeckCommands,
     },
-    /// updates an existing cards
-    Update {
-        /// the id of the card
-        #[arg(short, long)]
-        id: i64,
-
-        /// the front of the card
-        #[arg(short, long)]
-        front: Option<String>,
-
-        /// the back of the card
-        #[arg(short, long)]
-        back: Option<String>,
+    /// oversees the tags that organise cards across decks
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
     },
-    /// deletes an existing card
-    Delete {
-        /// the id of the card
-        #[arg(short, long)]
-        id: i64,
+    /// lists, restores or purges deleted cards and decks
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
     },
+    /// reviews the due cards of a deck and exits with a summary
+    Review(ReviewArgs),
+    /// shows, applies or reverts the migrations of the database
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+    /// fits personalised FSRS weights from the review history
+    Optimize(OptimizeArgs),
 }
 
 #[tokio::main]
-async fn main() -> Result<(), sqlx::Error> {
-    dotenv().ok();
-    let database_url = dotenv::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&database_url)
-        .await?;
-
+async fn main() -> Result<ExitCode, sqlx::Error> {
     let cli = Cli::parse();
 
-    match cli.command {
+    dotenv().ok();
+    let database_url =
+        dotenv::var("DATABASE_URL").unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string());
+    let pool = match connect(&database_url).await {
+        Ok(pool) => pool,
+        Err(e) => return Ok(cli::fail(format!("could not open {}: {}", database_url, e))),
+    };
+    // the db commands are how a database that fails to migrate gets looked at
+    if !matches!(cli.command, Some(Commands::Db { .. })) {
+        if let Err(e) = MIGRATOR.run(&pool).await {
+            return Ok(cli::fail(format!("could not migrate {}: {}", database_url, e)));
+        }
+        if let Err(e) = cli::trash::purge_expired(&pool).await {
+            return Ok(cli::fail(format!("could not empty the trash: {}", e)));
+        }
+    }
+
+    let code = match cli.command {
         Some(Commands::Start) => {
             println!("Starting app");
-            start_app(pool).await?;
+            start_app(pool, cli.format).await?;
+            ExitCode::SUCCESS
         }
-        _ => println!("no command given"),
-        // Some(Commands::Card { command }) => match command {
-        //     Some(CardCommands::List) => {
-        //         list_cards(&mut tx).await?;
-        //     }
-        //     Some(CardCommands::Create { front, back }) => {
-        //         create_card(&mut tx, front, back).await?;
-        //     }
-        //     Some(CardCommands::Update { id, front, back }) => {
-        //         update_card(&mut tx, id, front, back).await?;
-        //     }
-        //     Some(CardCommands::Delete { id }) => {
-        //         delete_card(&mut tx, id).await?;
-        //     }
-        //     None => println!("no command given"),
-        // },
-    }
+        Some(Commands::Card { command }) => cli::card::run(&pool, command, cli.format).await,
+        Some(Commands::Deck { command }) => cli::deck::run(&pool, command, cli.format).await,
+        Some(Commands::Db { command }) => cli::db::run(&pool, command, cli.format).await,
+        Some(Commands::Tag { command }) => cli::tag::run(&pool, command, cli.format).await,
+        Some(Commands::Trash { command }) => cli::trash::run(&pool, command, cli.format).await,
+        Some(Commands::Review(args)) => cli::review::run(&pool, args).await,
+        Some(Commands::Optimize(args)) => cli::optimize::run(&pool, args).await,
+        None => {
+            println!("no command given");
+            ExitCode::SUCCESS
+        }
+    };
 
-    Ok(())
+    Ok(code)
 }
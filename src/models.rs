@@ -1,7 +1,8 @@
-use bcrypt::{verify, hash, DEFAULT_COST};
+use serde::Serialize;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct User {
+    id: Option<i64>,
     username: String,
     is_authenticated: bool,
     is_guest: bool,
@@ -10,40 +11,160 @@ pub struct User {
 impl User {
     pub fn guest() -> Self {
         Self {
+            id: None,
             username: "guest".to_string(),
             is_authenticated: false,
             is_guest: true,
         }
     }
 
-    pub fn authenticate(&self, password: String) -> bool {
-        let hashed_password = hash(password, DEFAULT_COST).unwrap();
-        verify("password", &hashed_password).unwrap()
-    }
-
-    pub fn find_by_username(username: String) -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
+    /// A user whose password was verified against the `user` table.
+    pub fn authenticated(id: i64, username: String) -> Self {
+        Self {
+            id: Some(id),
             username,
-            is_authenticated: false,
+            is_authenticated: true,
             is_guest: false,
-        })
+        }
+    }
+
+    pub fn is_guest(&self) -> bool {
+        self.is_guest
+    }
+
+    /// The id of the user in the database, or `None` for guests.
+    pub fn id(&self) -> Option<i64> {
+        self.id
     }
 }
 
+/// The stored credentials of a user.
+pub struct UserCredentials {
+    pub id: i64,
+    pub username: String,
+    pub password_hash: String,
+}
+
 pub struct Card {
     pub id: Option<i64>,
     pub front: String,
     pub back: String,
 }
 
+#[derive(Serialize)]
 pub struct ListCard {
     pub id: i64,
     pub front: String,
     pub back: String,
+    /// The names of the tags of the card, sorted.
+    pub tags: Vec<String>,
 }
 
+/// A card found by a search.
+#[derive(Serialize)]
+pub struct CardMatch {
+    pub id: i64,
+    pub front: String,
+    pub back: String,
+    pub tags: Vec<String>,
+    /// The text around the words the search looked for, with each of them in brackets.
+    pub snippet: Option<String>,
+}
+
+#[derive(Serialize)]
 pub struct ListDeck {
     pub id: i64,
     pub name: String,
     pub description: Option<String>,
+    pub cards: i64,
+    pub new: i64,
+    pub due: i64,
+}
+
+#[derive(Serialize)]
+pub struct ListTag {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// The number of cards outside the trash with the tag.
+    pub cards: i64,
+}
+
+/// A card or deck in the trash.
+#[derive(Serialize)]
+pub struct TrashItem {
+    /// `card` or `deck`.
+    pub kind: String,
+    pub id: i64,
+    /// The front of a card or the name of a deck.
+    pub name: String,
+    pub deleted_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardSchedule {
+    pub ease_factor: f64,
+    pub interval_days: i64,
+    pub repetitions: i64,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+}
+
+impl Default for CardSchedule {
+    fn default() -> Self {
+        Self {
+            ease_factor: 2.5,
+            interval_days: 0,
+            repetitions: 0,
+            stability: None,
+            difficulty: None,
+        }
+    }
+}
+
+/// An attempt at recalling a card during a review session.
+pub struct Answer {
+    pub user_id: Option<i64>,
+    pub card_id: i64,
+    pub deck_id: i64,
+    pub session_id: i64,
+    pub answer: String,
+    pub correct_answer: String,
+    pub grade: i64,
+    pub elapsed_ms: i64,
+}
+
+/// A review session that was started but never ended.
+pub struct OpenSession {
+    pub id: i64,
+    pub deck_id: i64,
+    pub deck_name: String,
+    pub start_time: String,
+    /// The review mode the session was asked for, instead of the one of the deck.
+    pub review_mode: Option<String>,
+    /// Whether the cards the session started with were saved, which older sessions lack.
+    pub has_queue: bool,
+}
+
+/// The progress of a user on a deck.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeckStats {
+    pub cards: i64,
+    /// Cards the user has never reviewed.
+    pub new: i64,
+    /// Reviewed cards that are due again.
+    pub due: i64,
+    pub reviews: i64,
+    pub correct: i64,
+}
+
+impl DeckStats {
+    /// The share of answers that were correct, if any were given.
+    pub fn accuracy(&self) -> Option<f64> {
+        if self.reviews == 0 {
+            None
+        } else {
+            Some(self.correct as f64 / self.reviews as f64)
+        }
+    }
 }
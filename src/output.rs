@@ -0,0 +1,304 @@
+use crate::hierarchy::{depth, leaf};
+use crate::models::{CardMatch, ListCard, ListDeck, ListTag, TrashItem};
+use clap::ValueEnum;
+use serde::Serialize;
+use strum::Display;
+
+/// How listings are printed.
+#[derive(ValueEnum, Display, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    /// aligned columns for reading in a terminal
+    #[default]
+    Table,
+    /// a JSON array with an object per row
+    Json,
+    /// comma separated values with a header row
+    Csv,
+    /// tab separated values with a header row
+    Tsv,
+}
+
+/// Something that can be listed as a row of named columns.
+pub trait Listing: Serialize {
+    const HEADERS: &'static [&'static str];
+
+    fn fields(&self) -> Vec<String>;
+
+    /// The fields shown in a table, where rows can be laid out for reading rather than parsing.
+    fn table_fields(&self) -> Vec<String> {
+        self.fields()
+    }
+}
+
+impl Listing for ListCard {
+    const HEADERS: &'static [&'static str] = &["id", "front", "back", "tags"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.front.clone(),
+            self.back.clone(),
+            self.tags.join(", "),
+        ]
+    }
+}
+
+impl Listing for CardMatch {
+    const HEADERS: &'static [&'static str] = &["id", "front", "back", "tags", "snippet"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.front.clone(),
+            self.back.clone(),
+            self.tags.join(", "),
+            self.snippet.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Listing for ListDeck {
+    const HEADERS: &'static [&'static str] = &["id", "name", "description", "cards", "new", "due"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.description.clone().unwrap_or_default(),
+            self.cards.to_string(),
+            self.new.to_string(),
+            self.due.to_string(),
+        ]
+    }
+
+    /// Subdecks are indented under their parent with only the last level of their name.
+    fn table_fields(&self) -> Vec<String> {
+        let mut fields = self.fields();
+        fields[1] = format!("{}{}", "  ".repeat(depth(&self.name)), leaf(&self.name));
+        fields
+    }
+}
+
+impl Listing for ListTag {
+    const HEADERS: &'static [&'static str] = &["id", "name", "description", "cards"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.clone(),
+            self.description.clone().unwrap_or_default(),
+            self.cards.to_string(),
+        ]
+    }
+}
+
+impl Listing for TrashItem {
+    const HEADERS: &'static [&'static str] = &["kind", "id", "name", "deleted_at"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.kind.clone(),
+            self.id.to_string(),
+            self.name.clone(),
+            self.deleted_at.clone(),
+        ]
+    }
+}
+
+pub fn render<T: Listing>(rows: &[T], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => table(rows),
+        OutputFormat::Json => {
+            let mut json = serde_json::to_string_pretty(rows).expect("listings serialize to JSON");
+            json.push('\n');
+            json
+        }
+        OutputFormat::Csv => delimited(rows, ",", csv_field),
+        OutputFormat::Tsv => delimited(rows, "\t", tsv_field),
+    }
+}
+
+pub fn print_rows<T: Listing>(rows: &[T], format: OutputFormat) {
+    print!("{}", render(rows, format));
+}
+
+fn table<T: Listing>(rows: &[T]) -> String {
+    let rows: Vec<Vec<String>> = rows.iter().map(Listing::table_fields).collect();
+    let mut widths: Vec<usize> = T::HEADERS.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, field) in widths.iter_mut().zip(row) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let line = |fields: Vec<String>| {
+        let line = fields
+            .iter()
+            .zip(&widths)
+            .map(|(field, width)| format!("{:<width$}", field, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        format!("{}\n", line.trim_end())
+    };
+
+    let mut table = line(T::HEADERS.iter().map(|header| header.to_string()).collect());
+    table.push_str(
+        &line(widths.iter().map(|width| "-".repeat(*width)).collect()).replace(" | ", "-+-"),
+    );
+    for row in rows {
+        table.push_str(&line(row));
+    }
+    table
+}
+
+fn delimited<T: Listing>(rows: &[T], separator: &str, escape: fn(&str) -> String) -> String {
+    let line = |fields: Vec<String>| {
+        let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        format!("{}\n", fields.join(separator))
+    };
+
+    let mut output = line(T::HEADERS.iter().map(|header| header.to_string()).collect());
+    for row in rows {
+        output.push_str(&line(row.fields()));
+    }
+    output
+}
+
+/// Quotes a field when it contains a separator, a quote or a line break, as in RFC 4180.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escapes tabs and line breaks, which cannot be quoted in tab separated values.
+fn tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards() -> Vec<ListCard> {
+        vec![
+            ListCard {
+                id: 1,
+                front: "hola".to_string(),
+                back: "hello, hi".to_string(),
+                tags: vec!["greetings".to_string(), "spanish".to_string()],
+            },
+            ListCard {
+                id: 12,
+                front: "say \"hi\"".to_string(),
+                back: "two\tlines\n".to_string(),
+                tags: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_table() {
+        let cards = [
+            ListCard {
+                id: 1,
+                front: "hola".to_string(),
+                back: "hello".to_string(),
+                tags: vec!["spanish".to_string()],
+            },
+            ListCard {
+                id: 12,
+                front: "adiós".to_string(),
+                back: "bye".to_string(),
+                tags: vec![],
+            },
+        ];
+
+        assert_eq!(
+            render(&cards, OutputFormat::Table),
+            "id | front | back  | tags\n\
+             ---+-------+-------+--------\n\
+             1  | hola  | hello | spanish\n\
+             12 | adiós | bye   |\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_and_tsv() {
+        assert_eq!(
+            render(&cards(), OutputFormat::Csv),
+            "id,front,back,tags\n\
+             1,hola,\"hello, hi\",\"greetings, spanish\"\n\
+             12,\"say \"\"hi\"\"\",\"two\tlines\n\",\n"
+        );
+        assert_eq!(
+            render(&cards(), OutputFormat::Tsv),
+            "id\tfront\tback\ttags\n\
+             1\thola\thello, hi\tgreetings, spanish\n\
+             12\tsay \"hi\"\ttwo\\tlines\\n\t\n"
+        );
+    }
+
+    #[test]
+    fn test_deck_tree() {
+        let deck = |id, name: &str, cards| ListDeck {
+            id,
+            name: name.to_string(),
+            description: None,
+            cards,
+            new: cards,
+            due: 0,
+        };
+        let decks = [
+            deck(1, "languages", 3),
+            deck(2, "languages::spanish", 2),
+            deck(3, "languages::spanish::verbs", 1),
+        ];
+
+        assert_eq!(
+            render(&decks, OutputFormat::Table),
+            "id | name      | description | cards | new | due\n\
+             ---+-----------+-------------+-------+-----+----\n\
+             1  | languages |             | 3     | 3   | 0\n\
+             2  |   spanish |             | 2     | 2   | 0\n\
+             3  |     verbs |             | 1     | 1   | 0\n"
+        );
+        assert_eq!(
+            render(&decks[2..], OutputFormat::Csv),
+            "id,name,description,cards,new,due\n3,languages::spanish::verbs,,1,1,0\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let decks = [ListDeck {
+            id: 3,
+            name: "spanish".to_string(),
+            description: None,
+            cards: 2,
+            new: 1,
+            due: 1,
+        }];
+        let json: serde_json::Value =
+            serde_json::from_str(&render(&decks, OutputFormat::Json)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([{
+                "id": 3,
+                "name": "spanish",
+                "description": null,
+                "cards": 2,
+                "new": 1,
+                "due": 1
+            }])
+        );
+    }
+}
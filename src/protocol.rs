@@ -0,0 +1,242 @@
+use std::io::{self, BufRead, Write};
+
+use crate::grading::Grader;
+use crate::models::Card;
+use crate::review::{ReviewFrontend, ReviewMode};
+use crate::scheduler::Rating;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use strum::{Display, IntoEnumIterator};
+
+/// How a review talks to whoever is reviewing.
+#[derive(ValueEnum, Display, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum Protocol {
+    /// prompts for a person at a terminal
+    #[default]
+    Terminal,
+    /// a JSON object per line, for another program to show the cards
+    Jsonl,
+}
+
+/// Drives a review with one JSON object per line, so that another program can show the
+/// cards while ankirs keeps grading and scheduling them.
+///
+/// ankirs writes messages with a `type` of `session`, `card`, `result`, `summary`,
+/// `nothing_due` or `error`. After each `card` it reads the answer to that card, either
+/// `{"answer": "..."}` to have it graded like a typed answer, or `{"rating": "good"}` (or a
+/// grade from 1 to 4) to grade it directly. An answer may name the `card_id` it is for.
+/// Closing the input ends the session early with its `summary`.
+///
+/// The review always belongs to a user, as nobody would be there to claim the answers of a
+/// guest.
+pub struct JsonLines<R, W> {
+    input: R,
+    output: W,
+    /// The answer to the last card and its score, if it was graded, for the result message.
+    last_answer: Option<(String, Option<f64>)>,
+}
+
+impl<R: BufRead, W: Write> JsonLines<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            last_answer: None,
+        }
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), io::Error> {
+        writeln!(self.output, "{}", message)?;
+        self.output.flush()
+    }
+}
+
+fn parse_rating(rating: &Value) -> Option<Rating> {
+    match rating {
+        Value::Number(grade) => grade.as_i64().and_then(Rating::from_grade),
+        Value::String(name) => {
+            Rating::iter().find(|rating| rating.to_string().eq_ignore_ascii_case(name))
+        }
+        _ => None,
+    }
+}
+
+/// Reads an answer to a card, returning the answer, its rating and its score if it was graded.
+fn parse_answer(
+    line: &str,
+    card: &Card,
+    grader: &Grader,
+) -> Result<(String, Rating, Option<f64>), String> {
+    let message: Value = serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))?;
+
+    if let Some(card_id) = message.get("card_id") {
+        if card_id.as_i64() != card.id {
+            return Err(format!("expected an answer to card {}", json!(card.id)));
+        }
+    }
+
+    let answer = message.get("answer");
+    if answer.is_some_and(|answer| !answer.is_string()) {
+        return Err("the answer must be a string".to_string());
+    }
+    let answer = answer.and_then(Value::as_str);
+
+    match (answer, message.get("rating")) {
+        (answer, Some(rating)) => match parse_rating(rating) {
+            Some(rating) => Ok((answer.unwrap_or_default().trim().to_string(), rating, None)),
+            None => {
+                Err("the rating must be again, hard, good, easy or a grade from 1 to 4".to_string())
+            }
+        },
+        (Some(answer), None) => {
+            let score = grader.score(answer, &card.back);
+            Ok((answer.trim().to_string(), grader.rating(score), Some(score)))
+        }
+        (None, None) => Err("expected an answer or a rating".to_string()),
+    }
+}
+
+impl<R: BufRead, W: Write> ReviewFrontend for JsonLines<R, W> {
+    fn start(&mut self, session_id: i64, cards: usize) -> Result<(), io::Error> {
+        self.send(json!({"type": "session", "session_id": session_id, "cards": cards}))
+    }
+
+    fn nothing_due(&mut self) -> Result<(), io::Error> {
+        self.send(json!({"type": "nothing_due"}))
+    }
+
+    fn ask(
+        &mut self,
+        card: &Card,
+        mode: ReviewMode,
+        grader: &Grader,
+    ) -> Result<Option<(String, Rating)>, io::Error> {
+        let mut message = json!({
+            "type": "card",
+            "card_id": card.id,
+            "front": card.front,
+            "mode": mode.to_string(),
+        });
+        // in flip mode the other side reveals the back itself
+        if mode == ReviewMode::Flip {
+            message["back"] = json!(card.back);
+        }
+        self.send(message)?;
+
+        loop {
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_answer(&line, card, grader) {
+                Ok((answer, rating, score)) => {
+                    self.last_answer = Some((answer.clone(), score));
+                    return Ok(Some((answer, rating)));
+                }
+                Err(message) => self.send(json!({"type": "error", "message": message}))?,
+            }
+        }
+    }
+
+    fn scheduled(
+        &mut self,
+        card: &Card,
+        rating: Rating,
+        interval_days: i64,
+    ) -> Result<(), io::Error> {
+        let (answer, score) = self.last_answer.take().unwrap_or_default();
+        self.send(json!({
+            "type": "result",
+            "card_id": card.id,
+            "answer": answer,
+            "expected": card.back,
+            "score": score,
+            "rating": rating.to_string().to_lowercase(),
+            "interval_days": interval_days,
+        }))
+    }
+
+    fn finish(&mut self, correct: i64, incorrect: i64) -> Result<(), io::Error> {
+        self.send(json!({"type": "summary", "correct": correct, "incorrect": incorrect}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card() -> Card {
+        Card {
+            id: Some(7),
+            front: "hola".to_string(),
+            back: "hello".to_string(),
+        }
+    }
+
+    fn messages(output: &[u8]) -> Vec<Value> {
+        output
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_graded_answer() {
+        let input = b"{\"card_id\": 7, \"answer\": \"hello \"}\n";
+        let mut output = vec![];
+        let mut frontend = JsonLines::new(&input[..], &mut output);
+
+        let answer = frontend.ask(&card(), ReviewMode::Typed, &Grader::default());
+        assert_eq!(answer.unwrap(), Some(("hello".to_string(), Rating::Good)));
+        frontend.scheduled(&card(), Rating::Good, 1).unwrap();
+
+        assert_eq!(
+            messages(&output),
+            vec![
+                json!({"type": "card", "card_id": 7, "front": "hola", "mode": "typed"}),
+                json!({
+                    "type": "result",
+                    "card_id": 7,
+                    "answer": "hello",
+                    "expected": "hello",
+                    "score": 1.0,
+                    "rating": "good",
+                    "interval_days": 1,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_answers_are_reported() {
+        let input =
+            b"not json\n{\"card_id\": 8, \"rating\": 3}\n{\"rating\": 5}\n{\"rating\": \"Hard\"}\n";
+        let mut output = vec![];
+        let mut frontend = JsonLines::new(&input[..], &mut output);
+
+        let answer = frontend.ask(&card(), ReviewMode::Flip, &Grader::default());
+        assert_eq!(answer.unwrap(), Some((String::new(), Rating::Hard)));
+
+        let messages = messages(&output);
+        assert_eq!(messages[0]["back"], "hello");
+        assert_eq!(messages.len(), 4);
+        assert!(messages[1..]
+            .iter()
+            .all(|message| message["type"] == "error"));
+    }
+
+    #[test]
+    fn test_closed_input() {
+        let mut output = vec![];
+        let mut frontend = JsonLines::new(&b""[..], &mut output);
+
+        let answer = frontend.ask(&card(), ReviewMode::Typed, &Grader::default());
+        assert_eq!(answer.unwrap(), None);
+    }
+}
@@ -1,16 +1,25 @@
-use std::io;
-
-use crate::models::{Card, ListCard, ListDeck};
-use sqlx::{Acquire, Sqlite, Transaction};
+use crate::grading::Grader;
+use crate::hierarchy::ancestors;
+use crate::models::{
+    Answer, Card, CardMatch, CardSchedule, DeckStats, ListCard, ListDeck, ListTag, OpenSession,
+    TrashItem, UserCredentials,
+};
+use crate::review::ReviewMode;
+use crate::scheduler::optimizer::{self, format_weights, parse_weights, ReviewLog};
+use crate::scheduler::{Rating, SchedulerKind, WEIGHT_COUNT};
+use crate::search::Search;
+use sqlx::{Acquire, QueryBuilder, Row, Sqlite, Transaction};
+use std::fmt;
+use std::str::FromStr;
 
 use bcrypt::{hash, DEFAULT_COST};
 
-pub async fn _create_user(
+pub async fn create_user(
     tx: &mut Transaction<'_, Sqlite>,
     username: String,
     password: String,
 ) -> Result<i64, sqlx::Error> {
-    println!("Creating user with username: {}", username);
+    log::debug!("Creating user with username: {}", username);
     let password_hash = hash(password, DEFAULT_COST).unwrap();
     let id = sqlx::query!(
         "INSERT INTO user (username, password_hash) VALUES (?, ?) RETURNING id;",
@@ -24,12 +33,27 @@ pub async fn _create_user(
     Ok(id)
 }
 
+pub async fn query_user_credentials(
+    tx: &mut Transaction<'_, Sqlite>,
+    username: &str,
+) -> Result<Option<UserCredentials>, sqlx::Error> {
+    let credentials = sqlx::query_as!(
+        UserCredentials,
+        r#"SELECT id AS "id!", username, password_hash FROM user WHERE username = ?"#,
+        username
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(credentials)
+}
+
 pub async fn create_card(
     tx: &mut Transaction<'_, Sqlite>,
     front: String,
     back: String,
 ) -> Result<i64, sqlx::Error> {
-    println!("Creating card with front: {}, back: {}", front, back);
+    log::debug!("Creating card with front: {}, back: {}", front, back);
     let id = sqlx::query!(
         "INSERT INTO card (front, back) VALUES (?, ?) RETURNING id;",
         front,
@@ -42,438 +66,3612 @@ pub async fn create_card(
     Ok(id)
 }
 
-pub async fn list_cards(tx: &mut Transaction<'_, Sqlite>) -> Result<(), sqlx::Error> {
-    let cards = sqlx::query_as!(ListCard, "SELECT id, front, back FROM card")
-        .fetch_all(tx.acquire().await?)
-        .await?;
-
-    for card in cards {
-        println!(
-            "
-            {}: | {} | {} |
-            ",
-            card.id, card.front, card.back
-        );
+/// Splits the tag names of a card, which the listings concatenate with a unit separator.
+fn card_tags(tags: Option<String>) -> Vec<String> {
+    match tags {
+        Some(tags) => tags.split('\x1f').map(str::to_string).collect(),
+        None => vec![],
     }
+}
 
-    Ok(())
+pub async fn list_cards(tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<ListCard>, sqlx::Error> {
+    let cards = sqlx::query!(
+        r#"
+        SELECT
+            id AS "id!",
+            front,
+            back,
+            (SELECT GROUP_CONCAT(name, char(31)) FROM (
+                SELECT tag.name
+                FROM card_tag
+                JOIN tag ON tag.id = card_tag.tag_id
+                WHERE card_tag.card_id = card.id
+                ORDER BY tag.name)) AS "tags: String"
+        FROM card
+        WHERE deleted_at IS NULL
+        "#
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(cards
+        .into_iter()
+        .map(|card| ListCard {
+            id: card.id,
+            front: card.front,
+            back: card.back,
+            tags: card_tags(card.tags),
+        })
+        .collect())
 }
 
 pub async fn list_cards_for_deck(
     tx: &mut Transaction<'_, Sqlite>,
     deck_id: i64,
-) -> Result<(), sqlx::Error> {
-    let cards = sqlx::query_as!(
-        ListCard,
+) -> Result<Vec<ListCard>, sqlx::Error> {
+    let cards = sqlx::query!(
         r#"
-        SELECT id, front, back 
-        FROM card 
-        LEFT JOIN card_deck ON card.id = card_deck.card_id
-        WHERE deck_id = ?
+        SELECT
+            id AS "id!",
+            front,
+            back,
+            (SELECT GROUP_CONCAT(name, char(31)) FROM (
+                SELECT tag.name
+                FROM card_tag
+                JOIN tag ON tag.id = card_tag.tag_id
+                WHERE card_tag.card_id = card.id
+                ORDER BY tag.name)) AS "tags: String"
+        FROM card
+        WHERE id IN (SELECT card_id FROM deck_card WHERE deck_id = ?)
+        AND deleted_at IS NULL
         "#,
         deck_id
     )
     .fetch_all(tx.acquire().await?)
     .await?;
 
-    for card in cards {
-        println!(
-            "
-            {}: | {} | {} |
-            ",
-            card.id, card.front, card.back
-        );
-    }
-
-    Ok(())
+    Ok(cards
+        .into_iter()
+        .map(|card| ListCard {
+            id: card.id,
+            front: card.front,
+            back: card.back,
+            tags: card_tags(card.tags),
+        })
+        .collect())
 }
 
-pub async fn add_card_to_deck(
+/// Lists the cards outside the trash that have a tag or one of the tags under it.
+pub async fn list_cards_for_tag(
     tx: &mut Transaction<'_, Sqlite>,
-    card_id: i64,
-    deck_id: i64,
-) -> Result<(), sqlx::Error> {
-    println!(
-        "Adding card with id {} to deck with id {}",
-        card_id, deck_id
-    );
-    sqlx::query!(
-        "INSERT INTO card_deck (card_id, deck_id) VALUES (?, ?)",
-        card_id,
-        deck_id
+    tag_id: i64,
+) -> Result<Vec<ListCard>, sqlx::Error> {
+    let cards = sqlx::query!(
+        r#"
+        SELECT
+            id AS "id!",
+            front,
+            back,
+            (SELECT GROUP_CONCAT(name, char(31)) FROM (
+                SELECT tag.name
+                FROM card_tag
+                JOIN tag ON tag.id = card_tag.tag_id
+                WHERE card_tag.card_id = card.id
+                ORDER BY tag.name)) AS "tags: String"
+        FROM card
+        WHERE id IN (
+            SELECT card_tag.card_id
+            FROM card_tag
+            JOIN tag ON tag.id = card_tag.tag_id
+            JOIN tag AS parent ON parent.id = ?
+            WHERE tag.name = parent.name
+            OR substr(tag.name, 1, length(parent.name) + 2) = parent.name || '::')
+        AND deleted_at IS NULL
+        "#,
+        tag_id
     )
-    .execute(tx.acquire().await?)
+    .fetch_all(tx.acquire().await?)
     .await?;
 
-    Ok(())
+    Ok(cards
+        .into_iter()
+        .map(|card| ListCard {
+            id: card.id,
+            front: card.front,
+            back: card.back,
+            tags: card_tags(card.tags),
+        })
+        .collect())
 }
 
-pub async fn update_card(
+/// Lists the cards outside the trash that match a search, which looks at the schedules and
+/// the decks of the user.
+///
+/// When the search looks for text the best matches come first, each with a snippet of where
+/// the text was found, and the other cards follow in the order they were created.
+pub async fn search_cards(
     tx: &mut Transaction<'_, Sqlite>,
-    id: i64,
-    front: Option<String>,
-    back: Option<String>,
+    user_id: Option<i64>,
+    search: &Search,
+) -> Result<Vec<CardMatch>, sqlx::Error> {
+    let mut builder = QueryBuilder::new(
+        r#"
+        SELECT
+            card.id,
+            card.front,
+            card.back,
+            (SELECT GROUP_CONCAT(name, char(31)) FROM (
+                SELECT tag.name
+                FROM card_tag
+                JOIN tag ON tag.id = card_tag.tag_id
+                WHERE card_tag.card_id = card.id
+                ORDER BY tag.name)) AS tags,
+        "#,
+    );
+    match search.full_text_query() {
+        Some(query) => {
+            builder
+                .push(
+                    "(SELECT snippet(card_fts, -1, '[', ']', '…', 12) FROM card_fts \
+                    WHERE card_fts MATCH ",
+                )
+                .push_bind(query.clone())
+                .push(" AND rowid = card.id) AS snippet, ")
+                .push("(SELECT rank FROM card_fts WHERE card_fts MATCH ")
+                .push_bind(query)
+                .push(" AND rowid = card.id) AS relevance");
+        }
+        None => {
+            builder.push("NULL AS snippet, NULL AS relevance");
+        }
+    }
+    builder.push(" FROM card WHERE card.deleted_at IS NULL AND ");
+    search.push_condition(&mut builder, user_id);
+    // cards matched by something else than text have no rank and go last
+    builder.push(" ORDER BY relevance IS NULL, relevance, card.id");
+
+    let rows = builder.build().fetch_all(tx.acquire().await?).await?;
+    rows.into_iter()
+        .map(|row| {
+            Ok(CardMatch {
+                id: row.try_get("id")?,
+                front: row.try_get("front")?,
+                back: row.try_get("back")?,
+                tags: card_tags(row.try_get("tags")?),
+                snippet: row.try_get("snippet")?,
+            })
+        })
+        .collect()
+}
+
+/// Creates the tags above a tag that do not exist yet, so that every level can be looked up.
+async fn create_tag_ancestors(
+    tx: &mut Transaction<'_, Sqlite>,
+    name: &str,
 ) -> Result<(), sqlx::Error> {
-    println!("Updating card with id: {}", id);
-    match (front, back) {
-        (Some(front), Some(back)) => {
-            sqlx::query!(
-                "UPDATE card SET front = ?, back = ? WHERE id = ?",
-                front,
-                back,
-                id
-            )
+    for ancestor in ancestors(name) {
+        sqlx::query!("INSERT OR IGNORE INTO tag (name) VALUES (?)", ancestor)
             .execute(tx.acquire().await?)
             .await?;
-        }
-        (Some(front), None) => {
-            sqlx::query!("UPDATE card SET front = ? WHERE id = ?", front, id)
-                .execute(tx.acquire().await?)
-                .await?;
-        }
-        (None, Some(back)) => {
-            sqlx::query!("UPDATE card SET back = ? WHERE id = ?", back, id)
-                .execute(tx.acquire().await?)
-                .await?;
-        }
-        (None, None) => {
-            println!("No changes to make");
-        }
     }
 
     Ok(())
 }
 
-pub async fn create_deck(
+/// Creates a tag along with the tags above it, given a name like `lang::spanish::verbs`.
+pub async fn create_tag(
     tx: &mut Transaction<'_, Sqlite>,
     name: String,
     description: Option<String>,
-) -> Result<(), sqlx::Error> {
-    println!("Creating deck with name: {}", name);
-    sqlx::query!(
-        "INSERT INTO deck (name, description) VALUES (?, ?)",
+) -> Result<i64, sqlx::Error> {
+    log::debug!("Creating tag with name: {}", name);
+    create_tag_ancestors(tx, &name).await?;
+    let id = sqlx::query!(
+        "INSERT INTO tag (name, description) VALUES (?, ?) RETURNING id",
         name,
         description
     )
-    .execute(tx.acquire().await?)
+    .fetch_one(tx.acquire().await?)
+    .await?
+    .id;
+
+    Ok(id)
+}
+
+/// Lists the tags with the number of cards outside the trash that have them or one of the
+/// tags under them.
+pub async fn list_tags(tx: &mut Transaction<'_, Sqlite>) -> Result<Vec<ListTag>, sqlx::Error> {
+    let tags = sqlx::query_as!(
+        ListTag,
+        r#"
+        SELECT
+            tag.id AS "id!",
+            tag.name,
+            tag.description,
+            COUNT(DISTINCT card.id) AS "cards!: i64"
+        FROM tag
+        LEFT JOIN tag AS subtag
+            ON subtag.name = tag.name
+            OR substr(subtag.name, 1, length(tag.name) + 2) = tag.name || '::'
+        LEFT JOIN card_tag ON card_tag.tag_id = subtag.id
+        LEFT JOIN card ON card.id = card_tag.card_id AND card.deleted_at IS NULL
+        GROUP BY tag.id
+        ORDER BY tag.name
+        "#
+    )
+    .fetch_all(tx.acquire().await?)
     .await?;
 
-    Ok(())
+    Ok(tags)
 }
 
-pub async fn update_deck(
+/// Renames a tag and/or changes its description, returning whether it exists.
+///
+/// Renaming a tag renames the tags under it too, so `lang` to `languages` turns
+/// `lang::spanish` into `languages::spanish`.
+pub async fn update_tag(
     tx: &mut Transaction<'_, Sqlite>,
     id: i64,
-    name: String,
+    name: Option<String>,
     description: Option<String>,
-) -> Result<(), sqlx::Error> {
-    println!("Creating deck with name: {}", name);
+) -> Result<bool, sqlx::Error> {
+    log::debug!("Updating tag with id: {}", id);
+    let Some(old_name) = sqlx::query_scalar!("SELECT name FROM tag WHERE id = ?", id)
+        .fetch_optional(tx.acquire().await?)
+        .await?
+    else {
+        return Ok(false);
+    };
+
+    if let Some(name) = &name {
+        sqlx::query!(
+            r#"
+            UPDATE tag SET name = ?1 || substr(name, length(?2) + 1)
+            WHERE substr(name, 1, length(?2) + 2) = ?2 || '::'
+            "#,
+            name,
+            old_name
+        )
+        .execute(tx.acquire().await?)
+        .await?;
+    }
     sqlx::query!(
-        "UPDATE deck SET name = ?, description = ? WHERE id = ?",
+        "UPDATE tag SET name = COALESCE(?, name), description = COALESCE(?, description) WHERE id = ?",
         name,
         description,
         id
     )
     .execute(tx.acquire().await?)
     .await?;
+    if let Some(name) = &name {
+        create_tag_ancestors(tx, name).await?;
+    }
 
-    Ok(())
+    Ok(true)
 }
 
-pub async fn delete_deck(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<(), sqlx::Error> {
-    println!("Deleting deck with id: {}", id);
-    let res = sqlx::query!("DELETE FROM deck WHERE id = ?", id)
+/// Deletes a tag and the tags under it and takes them off every card, returning whether the
+/// tag existed.
+pub async fn delete_tag(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<bool, sqlx::Error> {
+    log::debug!("Deleting tag with id: {}", id);
+    sqlx::query!(
+        r#"
+        DELETE FROM tag
+        WHERE substr(name, 1, length((SELECT name FROM tag WHERE id = ?1)) + 2)
+            = (SELECT name FROM tag WHERE id = ?1) || '::'
+        "#,
+        id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+    let res = sqlx::query!("DELETE FROM tag WHERE id = ?", id)
         .execute(tx.acquire().await?)
         .await?
         .rows_affected();
 
-    if res == 0 {
-        println!("No deck with id: {} found", id);
-    } else {
-        println!("Deleted deck with id: {}", id);
-    }
-
-    Ok(())
+    Ok(res > 0)
 }
 
-pub async fn list_decks(tx: &mut Transaction<'_, Sqlite>) -> Result<(), sqlx::Error> {
-    let decks = sqlx::query_as!(ListDeck, "SELECT id, name, description FROM deck")
-        .fetch_all(tx.acquire().await?)
-        .await?;
-
-    for deck in decks {
-        println!(
-            "
-            {}: | {} | {} |
-            ",
-            deck.id,
-            deck.name,
-            deck.description.unwrap_or("".to_string())
-        );
-    }
+/// Finds a tag by its id or, failing that, by its name.
+pub async fn query_tag_id(
+    tx: &mut Transaction<'_, Sqlite>,
+    id_or_name: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+        SELECT id AS "id!"
+        FROM tag
+        WHERE CAST(id AS TEXT) = ?1 OR name = ?1
+        ORDER BY CAST(id AS TEXT) = ?1 DESC
+        LIMIT 1
+        "#,
+        id_or_name
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
 
-    Ok(())
+    Ok(res.map(|res| res.id))
 }
 
-pub async fn delete_card(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<(), sqlx::Error> {
-    println!("Deleting card with id: {}", id);
-    let res = sqlx::query!("DELETE FROM card WHERE id = ?", id)
-        .execute(tx.acquire().await?)
-        .await?
-        .rows_affected();
-
-    if res == 0 {
-        println!("No card with id: {} found", id);
-    } else {
-        println!("Deleted card with id: {}", id);
-    }
+/// Puts a tag on a card, returning whether the card did not have it yet.
+pub async fn tag_card(
+    tx: &mut Transaction<'_, Sqlite>,
+    card_id: i64,
+    tag_id: i64,
+) -> Result<bool, sqlx::Error> {
+    log::debug!("Tagging card with id {} with tag {}", card_id, tag_id);
+    let res = sqlx::query!(
+        "INSERT OR IGNORE INTO card_tag (card_id, tag_id) VALUES (?, ?)",
+        card_id,
+        tag_id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
 
-    Ok(())
+    Ok(res > 0)
 }
 
-pub async fn query_deck_exists(
+/// Takes a tag off a card, returning whether the card had it.
+pub async fn untag_card(
     tx: &mut Transaction<'_, Sqlite>,
-    id: i64,
+    card_id: i64,
+    tag_id: i64,
 ) -> Result<bool, sqlx::Error> {
-    let res = sqlx::query!("SELECT id FROM deck WHERE id = ?", id)
-        .fetch_optional(tx.acquire().await?)
-        .await?;
+    log::debug!("Untagging card with id {} from tag {}", card_id, tag_id);
+    let res = sqlx::query!(
+        "DELETE FROM card_tag WHERE card_id = ? AND tag_id = ?",
+        card_id,
+        tag_id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
 
-    println!("Deck with id: {} exists: {}", id, res.is_some());
-    Ok(res.is_some())
+    Ok(res > 0)
 }
 
-pub async fn query_deck_info(tx: &mut Transaction<'_, Sqlite>, id: i64) -> String {
-    let res = sqlx::query!("SELECT * FROM deck WHERE id = ?", id)
-        .fetch_one(tx.acquire().await.unwrap())
-        .await
-        .unwrap();
+pub async fn add_card_to_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    card_id: i64,
+    deck_id: i64,
+) -> Result<(), sqlx::Error> {
+    log::debug!(
+        "Adding card with id {} to deck with id {}",
+        card_id,
+        deck_id
+    );
+    sqlx::query!(
+        "INSERT INTO card_deck (card_id, deck_id) VALUES (?, ?)",
+        card_id,
+        deck_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
 
-    let (id, name, desc) = (res.id, res.name, res.description.unwrap_or("".to_string()));
+/// Takes a card out of a deck without deleting it, returning whether it was in the deck.
+pub async fn remove_card_from_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    card_id: i64,
+    deck_id: i64,
+) -> Result<bool, sqlx::Error> {
+    log::debug!(
+        "Removing card with id {} from deck with id {}",
+        card_id,
+        deck_id
+    );
+    let res = sqlx::query!(
+        "DELETE FROM card_deck WHERE card_id = ? AND deck_id = ?",
+        card_id,
+        deck_id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
 
-    format!("{id}, {name}, {desc}").to_string()
+    Ok(res > 0)
 }
 
-pub async fn review_deck(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<(), sqlx::Error> {
-    // TODO: add limit
-    let mut cards = sqlx::query_as!(
-        Card,
+/// Changes the front and/or back of a card, returning whether the card exists outside the trash.
+pub async fn update_card(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+    front: Option<String>,
+    back: Option<String>,
+) -> Result<bool, sqlx::Error> {
+    log::debug!("Updating card with id: {}", id);
+    let res = sqlx::query!(
         r#"
-        SELECT id, front, back 
-        FROM card 
-        WHERE id IN (
-            SELECT card_id 
-            FROM card_deck 
-            WHERE deck_id = ?)
-        ORDER BY RANDOM();
+        UPDATE card SET front = COALESCE(?, front), back = COALESCE(?, back)
+        WHERE id = ? AND deleted_at IS NULL
         "#,
+        front,
+        back,
         id
     )
-    .fetch_all(tx.acquire().await?)
-    .await?;
-
-    let mut correct = 0;
-    let mut incorrect = 0;
-
-    while !cards.is_empty() {
-        let card = cards.pop().unwrap();
-        println!("Front: {}", card.front);
-        println!("What is the back?");
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
-
-        // TODO: add similarity function
-        if input.trim() == card.back {
-            println!("Correct!");
-            correct += 1;
-        } else {
-            println!("Incorrect!");
-            incorrect += 1;
-            cards.push(card);
-        }
-    }
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
 
-    println!("You got {} correct and {} incorrect", correct, incorrect);
+    Ok(res > 0)
+}
 
-    Ok(())
+/// Why a deck could not be created or renamed.
+#[derive(Debug)]
+pub enum DeckError {
+    /// The name belongs to a deck in the trash, which keeps it until it is restored or purged.
+    InTrash(String),
+    /// A deck above it is private, and only private decks of its owner can go under it.
+    PrivateAncestor(String),
+    Database(sqlx::Error),
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use dotenv::dotenv;
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeckError::InTrash(name) => write!(
+                f,
+                "a deck named {} is in the trash, restore or purge it first",
+                name
+            ),
+            DeckError::PrivateAncestor(name) => write!(
+                f,
+                "a deck named {} above it is private, only its owner can put decks under it",
+                name
+            ),
+            DeckError::Database(e) => e.fmt(f),
+        }
+    }
+}
 
-    async fn create_transaction() -> Transaction<'static, Sqlite> {
-        dotenv().ok();
-        let database_url = dotenv::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let conn = sqlx::sqlite::SqlitePoolOptions::new()
-            .connect(&database_url)
-            .await
-            .unwrap();
+impl std::error::Error for DeckError {}
 
-        conn.begin().await.unwrap()
+impl From<sqlx::Error> for DeckError {
+    fn from(e: sqlx::Error) -> Self {
+        DeckError::Database(e)
     }
+}
 
-    #[tokio::test]
-    async fn test_create_card() {
-        let mut tx = create_transaction().await;
+/// Returns the name of the deck in the trash that keeps a deck from being named `name`, which
+/// is either a deck of that name or a deck above it.
+///
+/// Decks in the trash keep their names until they are purged, and a deck in the trash would
+/// hide the subdecks created under it.
+pub async fn query_trashed_deck_name(
+    tx: &mut Transaction<'_, Sqlite>,
+    name: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let res = sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM deck
+        WHERE deleted_at IS NOT NULL
+        AND (name = ?1 OR substr(?1, 1, length(name) + 2) = name || '::')
+        ORDER BY length(name)
+        LIMIT 1
+        "#,
+        name
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
 
-        create_card(&mut tx, "front".to_string(), "back".to_string())
-            .await
-            .unwrap();
+    Ok(res)
+}
 
-        let card = sqlx::query!("SELECT front, back FROM card WHERE id = last_insert_rowid();")
-            .fetch_one(tx.acquire().await.unwrap())
-            .await
-            .unwrap();
+/// Returns the name of a deck above a deck named `name` that `owner_id` cannot see, which
+/// keeps the deck from being created with that owner.
+///
+/// A deck visible to someone who cannot see the decks above it would be cut off from them.
+pub async fn query_private_ancestor_name(
+    tx: &mut Transaction<'_, Sqlite>,
+    owner_id: Option<i64>,
+    name: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let res = sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM deck
+        WHERE owner_id IS NOT NULL AND owner_id IS NOT ?2
+        AND substr(?1, 1, length(name) + 2) = name || '::'
+        ORDER BY length(name)
+        LIMIT 1
+        "#,
+        name,
+        owner_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
 
-        assert_eq!(card.front, "front");
-        assert_eq!(card.back, "back");
+    Ok(res)
+}
 
-        tx.rollback().await.unwrap();
+/// Creates the decks above a deck that do not exist yet, with the same owner.
+///
+/// Callers check `query_trashed_deck_name` and `query_private_ancestor_name` first, as a deck
+/// above in the trash is kept and one the owner cannot see must not be used.
+async fn create_deck_ancestors(
+    tx: &mut Transaction<'_, Sqlite>,
+    name: &str,
+    owner_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    for ancestor in ancestors(name) {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO deck (name, owner_id) VALUES (?, ?)",
+            ancestor,
+            owner_id
+        )
+        .execute(tx.acquire().await?)
+        .await?;
     }
 
-    #[tokio::test]
-    async fn test_update_card() {
-        let mut tx = create_transaction().await;
+    Ok(())
+}
 
-        let record_id = sqlx::query!(
-            "INSERT INTO card (front, back) VALUES (?, ?) RETURNING id",
-            "front",
-            "back"
-        )
-        .fetch_one(tx.acquire().await.unwrap())
-        .await
-        .unwrap()
-        .id;
+/// Creates a deck, private to `owner_id` when given and shared with everyone otherwise.
+///
+/// A name like `Languages::Spanish` makes it a subdeck, creating the decks above it as needed.
+pub async fn create_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    name: String,
+    description: Option<String>,
+    owner_id: Option<i64>,
+) -> Result<i64, DeckError> {
+    log::debug!("Creating deck with name: {}", name);
+    if let Some(trashed) = query_trashed_deck_name(tx, &name).await? {
+        return Err(DeckError::InTrash(trashed));
+    }
+    if let Some(private) = query_private_ancestor_name(tx, owner_id, &name).await? {
+        return Err(DeckError::PrivateAncestor(private));
+    }
+    create_deck_ancestors(tx, &name, owner_id).await?;
+    let id = sqlx::query!(
+        "INSERT INTO deck (name, description, owner_id) VALUES (?, ?, ?) RETURNING id",
+        name,
+        description,
+        owner_id
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?
+    .id;
+
+    Ok(id)
+}
+
+/// Creates a filtered deck that borrows at most `limit` of the cards matching a search, or all
+/// of them without a limit. The deck is empty until it is rebuilt.
+pub async fn create_filtered_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    name: String,
+    description: Option<String>,
+    search: String,
+    limit: Option<i64>,
+    owner_id: Option<i64>,
+) -> Result<i64, DeckError> {
+    let id = create_deck(tx, name, description, owner_id).await?;
+    sqlx::query!(
+        "UPDATE deck SET search = ?, search_limit = ? WHERE id = ?",
+        search,
+        limit,
+        id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(id)
+}
+
+/// Returns the search of a filtered deck, or `None` for other decks.
+pub async fn query_deck_search(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+) -> Result<Option<String>, sqlx::Error> {
+    let res = sqlx::query_scalar!("SELECT search FROM deck WHERE id = ?", id)
+        .fetch_optional(tx.acquire().await?)
+        .await?;
+
+    Ok(res.flatten())
+}
+
+/// Gives the cards borrowed by a filtered deck back to their home decks, returning how many
+/// there were.
+pub async fn empty_filtered_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+) -> Result<u64, sqlx::Error> {
+    log::debug!("Emptying filtered deck with id: {}", id);
+    let res = sqlx::query!("DELETE FROM filtered_card WHERE deck_id = ?", id)
+        .execute(tx.acquire().await?)
+        .await?
+        .rows_affected();
+
+    Ok(res)
+}
+
+/// Gives a card back to its home decks if a filtered deck borrowed it.
+pub async fn return_filtered_card(
+    tx: &mut Transaction<'_, Sqlite>,
+    card_id: i64,
+    deck_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM filtered_card WHERE card_id = ? AND deck_id = ?",
+        card_id,
+        deck_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
+
+/// Empties a filtered deck and borrows the cards matching its search again, returning how many
+/// it borrowed.
+///
+/// The search looks at the schedules of the owner of the deck. Cards in the trash, suspended
+/// cards and cards borrowed by another filtered deck the owner can see are left out, and the
+/// cards that have been due the longest are borrowed first.
+pub async fn rebuild_filtered_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+) -> Result<u64, sqlx::Error> {
+    log::debug!("Rebuilding filtered deck with id: {}", id);
+    empty_filtered_deck(tx, id).await?;
+    let deck = sqlx::query!(
+        "SELECT search, search_limit, owner_id FROM deck WHERE id = ?",
+        id
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?;
+    let Some(search) = deck
+        .search
+        .and_then(|search| Search::from_str(&search).ok())
+    else {
+        return Ok(0);
+    };
+
+    let mut builder =
+        QueryBuilder::new("INSERT INTO filtered_card (card_id, deck_id) SELECT card.id, ");
+    builder
+        .push_bind(id)
+        .push(
+            " FROM card LEFT JOIN card_schedule AS schedule \
+            ON schedule.card_id = card.id AND schedule.user_id IS ",
+        )
+        .push_bind(deck.owner_id)
+        .push(
+            " WHERE card.deleted_at IS NULL AND NOT card.suspended \
+            AND card.id NOT IN (SELECT card_id FROM filtered_card \
+            JOIN deck AS filtered ON filtered.id = filtered_card.deck_id \
+            WHERE filtered.owner_id IS NULL OR filtered.owner_id = ",
+        )
+        .push_bind(deck.owner_id)
+        .push(") AND ");
+    search.push_condition(&mut builder, deck.owner_id);
+    // new cards have no due date and come after the ones due
+    builder
+        .push(" ORDER BY schedule.due_at IS NULL, schedule.due_at, card.id LIMIT ")
+        .push_bind(deck.search_limit.unwrap_or(-1));
+    let res = builder
+        .build()
+        .execute(tx.acquire().await?)
+        .await?
+        .rows_affected();
+
+    Ok(res)
+}
+
+/// Renames a deck and/or changes its description, returning whether the user can see it.
+/// A description of `Some(None)` clears it.
+///
+/// Renaming a deck renames the subdecks the user can see too, so that they stay under it.
+pub async fn update_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    id: i64,
+    name: Option<String>,
+    description: Option<Option<String>>,
+) -> Result<bool, DeckError> {
+    log::debug!("Updating deck with id: {}", id);
+    let Some(deck) = sqlx::query!(
+        r#"
+        SELECT name, owner_id
+        FROM deck
+        WHERE id = ? AND (owner_id IS NULL OR owner_id = ?) AND deleted_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?
+    else {
+        return Ok(false);
+    };
+    if let Some(name) = &name {
+        if let Some(trashed) = query_trashed_deck_name(tx, name).await? {
+            return Err(DeckError::InTrash(trashed));
+        }
+        if let Some(private) = query_private_ancestor_name(tx, deck.owner_id, name).await? {
+            return Err(DeckError::PrivateAncestor(private));
+        }
+    }
+
+    // the deck goes first, so that a name that is taken fails before any subdeck moves
+    let set_description = description.is_some();
+    let description = description.flatten();
+    sqlx::query!(
+        r#"
+        UPDATE deck
+        SET name = COALESCE(?, name), description = IIF(?, ?, description)
+        WHERE id = ?
+        "#,
+        name,
+        set_description,
+        description,
+        id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+    if let Some(name) = &name {
+        sqlx::query!(
+            r#"
+            UPDATE deck SET name = ?1 || substr(name, length(?2) + 1)
+            WHERE substr(name, 1, length(?2) + 2) = ?2 || '::' AND id != ?3
+            AND (owner_id IS NULL OR owner_id = ?4)
+            "#,
+            name,
+            deck.name,
+            id,
+            user_id
+        )
+        .execute(tx.acquire().await?)
+        .await?;
+        create_deck_ancestors(tx, name, deck.owner_id).await?;
+    }
+
+    Ok(true)
+}
+
+/// Moves a deck the user can see and the subdecks they can see to the trash, returning
+/// whether the user could see it.
+///
+/// With `delete_cards` the cards that are in no other deck go to the trash along with them,
+/// otherwise every card is kept. The decks keep their cards in the trash so that restoring
+/// them brings them back.
+pub async fn delete_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    id: i64,
+    delete_cards: bool,
+) -> Result<bool, sqlx::Error> {
+    log::debug!("Deleting deck with id: {}", id);
+    if !query_deck_visible(tx, user_id, id).await? {
+        return Ok(false);
+    }
+
+    // subdecks that are already in the trash keep their own time
+    sqlx::query!(
+        r#"
+        UPDATE deck SET deleted_at = CURRENT_TIMESTAMP
+        WHERE id IN (SELECT deck_id FROM deck_tree WHERE ancestor_id = ?)
+        AND (owner_id IS NULL OR owner_id = ?)
+        AND deleted_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    // filtered decks give their cards back rather than keeping them in the trash
+    sqlx::query!(
+        r#"
+        DELETE FROM filtered_card
+        WHERE deck_id IN (
+            SELECT deck_tree.deck_id
+            FROM deck_tree
+            JOIN deck ON deck.id = deck_tree.deck_id
+            WHERE deck_tree.ancestor_id = ? AND (deck.owner_id IS NULL OR deck.owner_id = ?))
+        "#,
+        id,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    if delete_cards {
+        // the cards are recorded with their decks, which is how restoring them finds them
+        sqlx::query!(
+            r#"
+            INSERT OR IGNORE INTO trashed_card (card_id, deck_id)
+            SELECT card_deck.card_id, card_deck.deck_id
+            FROM card_deck
+            JOIN deck_tree ON deck_tree.deck_id = card_deck.deck_id
+            JOIN deck ON deck.id = card_deck.deck_id
+            JOIN card ON card.id = card_deck.card_id
+            WHERE deck_tree.ancestor_id = ?1
+            AND deck.deleted_at = (SELECT deleted_at FROM deck WHERE id = ?1)
+            AND card.deleted_at IS NULL
+            AND card.id NOT IN (
+                SELECT card_id
+                FROM card_deck
+                JOIN deck ON deck.id = card_deck.deck_id
+                WHERE deck.deleted_at IS NULL)
+            "#,
+            id
+        )
+        .execute(tx.acquire().await?)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE card SET deleted_at = (SELECT deleted_at FROM deck WHERE id = ?1)
+            WHERE id IN (
+                SELECT card_id
+                FROM trashed_card
+                JOIN deck_tree ON deck_tree.deck_id = trashed_card.deck_id
+                WHERE deck_tree.ancestor_id = ?1)
+            AND deleted_at IS NULL
+            "#,
+            id
+        )
+        .execute(tx.acquire().await?)
+        .await?;
+    }
+
+    Ok(true)
+}
+
+/// Lists the shared decks and the decks owned by the user, each followed by its subdecks.
+///
+/// The card counts of a deck include the cards of its subdecks, and a card that is in
+/// several of them is counted once.
+pub async fn list_decks(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+) -> Result<Vec<ListDeck>, sqlx::Error> {
+    let decks = sqlx::query_as!(
+        ListDeck,
+        r#"
+        WITH tree_card AS (
+            SELECT DISTINCT
+                deck_tree.ancestor_id,
+                card.id,
+                card_schedule.card_id IS NULL AS new,
+                card_schedule.due_at <= CURRENT_TIMESTAMP AS due
+            FROM deck_tree
+            JOIN deck ON deck.id = deck_tree.deck_id
+            JOIN deck_card ON deck_card.deck_id = deck.id
+            JOIN card ON card.id = deck_card.card_id AND card.deleted_at IS NULL
+            LEFT JOIN card_schedule
+                ON card_schedule.card_id = card.id
+                AND card_schedule.user_id IS ?1
+            WHERE (deck.owner_id IS NULL OR deck.owner_id = ?1) AND deck.deleted_at IS NULL
+        )
+        SELECT
+            deck.id AS "id!",
+            deck.name,
+            deck.description,
+            COUNT(tree_card.id) AS "cards!: i64",
+            COUNT(*) FILTER (WHERE tree_card.new) AS "new!: i64",
+            COUNT(*) FILTER (WHERE tree_card.due) AS "due!: i64"
+        FROM deck
+        LEFT JOIN tree_card ON tree_card.ancestor_id = deck.id
+        WHERE (deck.owner_id IS NULL OR deck.owner_id = ?1) AND deck.deleted_at IS NULL
+        GROUP BY deck.id
+        ORDER BY replace(deck.name, '::', char(1))
+        "#,
+        user_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(decks)
+}
+
+/// Suspends a card or brings it back into reviews, returning whether it exists outside the
+/// trash.
+pub async fn set_card_suspended(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+    suspended: bool,
+) -> Result<bool, sqlx::Error> {
+    log::debug!(
+        "Setting suspended to {} for card with id: {}",
+        suspended,
+        id
+    );
+    let res = sqlx::query!(
+        "UPDATE card SET suspended = ? WHERE id = ? AND deleted_at IS NULL",
+        suspended,
+        id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+
+    Ok(res > 0)
+}
+
+/// Moves a card to the trash, returning whether it existed outside of it.
+pub async fn delete_card(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<bool, sqlx::Error> {
+    log::debug!("Deleting card with id: {}", id);
+    let res = sqlx::query!(
+        "UPDATE card SET deleted_at = CURRENT_TIMESTAMP WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+
+    Ok(res > 0)
+}
+
+/// Lists the decks in the trash that the user can see and the cards in it that are in no
+/// deck of another user, oldest first.
+pub async fn list_trash(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+) -> Result<Vec<TrashItem>, sqlx::Error> {
+    let items = sqlx::query_as!(
+        TrashItem,
+        r#"
+        SELECT
+            'deck' AS "kind!: String",
+            id AS "id!",
+            name AS "name!",
+            CAST(deleted_at AS TEXT) AS "deleted_at!: String"
+        FROM deck
+        WHERE deleted_at IS NOT NULL AND (owner_id IS NULL OR owner_id = ?)
+        UNION ALL
+        SELECT 'card', id, front, CAST(deleted_at AS TEXT)
+        FROM card
+        WHERE deleted_at IS NOT NULL
+        AND id NOT IN (
+            SELECT card_id
+            FROM card_deck
+            JOIN deck ON deck.id = card_deck.deck_id
+            WHERE deck.owner_id IS NOT NULL AND deck.owner_id IS NOT ?1)
+        ORDER BY 4, 1, 2
+        "#,
+        user_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(items)
+}
+
+/// Takes a card out of the trash, returning whether it was in it.
+pub async fn restore_card(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<bool, sqlx::Error> {
+    log::debug!("Restoring card with id: {}", id);
+    let res = sqlx::query!(
+        "UPDATE card SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+        id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+    sqlx::query!("DELETE FROM trashed_card WHERE card_id = ?", id)
+        .execute(tx.acquire().await?)
+        .await?;
+
+    Ok(res > 0)
+}
+
+/// Takes a deck the user can see out of the trash along with the subdecks they can see and
+/// the cards that were trashed with it, returning whether it was in the trash.
+pub async fn restore_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    log::debug!("Restoring deck with id: {}", id);
+    let Some(deleted_at) = sqlx::query_scalar!(
+        r#"
+        SELECT deleted_at AS "deleted_at!: String"
+        FROM deck
+        WHERE id = ? AND (owner_id IS NULL OR owner_id = ?) AND deleted_at IS NOT NULL
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE card SET deleted_at = NULL
+        WHERE id IN (
+            SELECT card_id
+            FROM trashed_card
+            JOIN deck_tree ON deck_tree.deck_id = trashed_card.deck_id
+            JOIN deck ON deck.id = trashed_card.deck_id
+            WHERE deck_tree.ancestor_id = ?1 AND deck.deleted_at = ?2
+            AND (deck.owner_id IS NULL OR deck.owner_id = ?3))
+        AND deleted_at IS NOT NULL
+        "#,
+        id,
+        deleted_at,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+    sqlx::query!(
+        r#"
+        DELETE FROM trashed_card
+        WHERE card_id IN (SELECT id FROM card WHERE deleted_at IS NULL)
+        "#
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE deck SET deleted_at = NULL
+        WHERE id IN (SELECT deck_id FROM deck_tree WHERE ancestor_id = ?1)
+        AND (owner_id IS NULL OR owner_id = ?3)
+        AND deleted_at = ?2
+        "#,
+        id,
+        deleted_at,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+    // a subdeck is never left under a deck in the trash, so the decks above come back too,
+    // without the rest of their subdecks
+    sqlx::query!(
+        r#"
+        UPDATE deck SET deleted_at = NULL
+        WHERE id IN (SELECT ancestor_id FROM deck_tree WHERE deck_id = ?)
+        AND (owner_id IS NULL OR owner_id = ?)
+        AND deleted_at IS NOT NULL
+        "#,
+        id,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(true)
+}
+
+/// Deletes for good the decks in the trash that the user can see and the cards in it that are
+/// in no deck of another user, returning how many were deleted.
+///
+/// The answers given in a purged deck are kept without their deck, as the FSRS weights are
+/// fitted on them. The answers to a purged card go with it.
+pub async fn purge_trash(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+) -> Result<u64, sqlx::Error> {
+    log::debug!("Purging the trash");
+    let decks = sqlx::query!(
+        "DELETE FROM deck WHERE deleted_at IS NOT NULL AND (owner_id IS NULL OR owner_id = ?)",
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+    let cards = sqlx::query!(
+        r#"
+        DELETE FROM card
+        WHERE deleted_at IS NOT NULL
+        AND id NOT IN (
+            SELECT card_id
+            FROM card_deck
+            JOIN deck ON deck.id = card_deck.deck_id
+            WHERE deck.owner_id IS NOT NULL AND deck.owner_id IS NOT ?)
+        "#,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+
+    Ok(decks + cards)
+}
+
+/// Deletes for good every card and deck that has been in the trash for longer than
+/// `retention_days`, returning how many were deleted.
+pub async fn purge_expired_trash(
+    tx: &mut Transaction<'_, Sqlite>,
+    retention_days: i64,
+) -> Result<u64, sqlx::Error> {
+    let decks = sqlx::query!(
+        "DELETE FROM deck WHERE deleted_at < datetime('now', '-' || ? || ' days')",
+        retention_days
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+    let cards = sqlx::query!(
+        "DELETE FROM card WHERE deleted_at < datetime('now', '-' || ? || ' days')",
+        retention_days
+    )
+    .execute(tx.acquire().await?)
+    .await?
+    .rows_affected();
+
+    Ok(decks + cards)
+}
+
+// decks are looked up with query_deck_id or query_deck_visible outside of the tests
+#[cfg(test)]
+pub async fn query_deck_exists(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "SELECT id FROM deck WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    log::debug!("Deck with id: {} exists: {}", id, res.is_some());
+    Ok(res.is_some())
+}
+
+/// Finds a deck the user can see by its id or, failing that, by its name.
+pub async fn query_deck_id(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    id_or_name: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+        SELECT id AS "id!"
+        FROM deck
+        WHERE (CAST(id AS TEXT) = ?1 OR name = ?1)
+        AND (owner_id IS NULL OR owner_id = ?2)
+        AND deleted_at IS NULL
+        ORDER BY CAST(id AS TEXT) = ?1 DESC
+        LIMIT 1
+        "#,
+        id_or_name,
+        user_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(res.map(|res| res.id))
+}
+
+/// Whether a card exists outside the trash.
+pub async fn query_card_exists(
+    tx: &mut Transaction<'_, Sqlite>,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        "SELECT id FROM card WHERE id = ? AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(res.is_some())
+}
+
+/// Whether a deck exists outside the trash and is shared or owned by the user.
+pub async fn query_deck_visible(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    id: i64,
+) -> Result<bool, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+        SELECT id
+        FROM deck
+        WHERE id = ? AND (owner_id IS NULL OR owner_id = ?) AND deleted_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(res.is_some())
+}
+
+pub async fn query_deck_info(tx: &mut Transaction<'_, Sqlite>, id: i64) -> String {
+    let res = sqlx::query!("SELECT * FROM deck WHERE id = ?", id)
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+    let (id, name, desc, scheduler, review_mode, grading) = (
+        res.id,
+        res.name,
+        res.description.unwrap_or("".to_string()),
+        res.scheduler,
+        res.review_mode,
+        res.grading,
+    );
+
+    format!(
+        "{id}, {name}, {desc}, scheduled with {scheduler}, reviewed in {review_mode} mode, \
+        graded with {grading}"
+    )
+    .to_string()
+}
+
+/// Returns the progress of a user on a deck and the subdecks they can see.
+pub async fn query_deck_stats(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: i64,
+) -> Result<DeckStats, sqlx::Error> {
+    let cards = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "cards!: i64",
+            COUNT(*) FILTER (WHERE card_schedule.card_id IS NULL) AS "new!: i64",
+            COUNT(*) FILTER (WHERE card_schedule.due_at <= CURRENT_TIMESTAMP) AS "due!: i64"
+        FROM card
+        LEFT JOIN card_schedule
+            ON card_schedule.card_id = card.id
+            AND card_schedule.user_id IS ?1
+        WHERE card.id IN (
+            SELECT card_id
+            FROM deck_card
+            JOIN deck_tree ON deck_tree.deck_id = deck_card.deck_id
+            JOIN deck ON deck.id = deck_card.deck_id
+            WHERE deck_tree.ancestor_id = ?2
+            AND (deck.id = ?2
+                OR (deck.owner_id IS NULL OR deck.owner_id = ?1) AND deck.deleted_at IS NULL))
+        AND card.deleted_at IS NULL
+        "#,
+        user_id,
+        deck_id
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?;
+
+    let answers = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) AS "reviews!: i64",
+            COUNT(*) FILTER (WHERE grade > 1) AS "correct!: i64"
+        FROM answer
+        WHERE deck_id IN (
+            SELECT deck_id
+            FROM deck_tree
+            JOIN deck ON deck.id = deck_tree.deck_id
+            WHERE deck_tree.ancestor_id = ?2
+            AND (deck.id = ?2
+                OR (deck.owner_id IS NULL OR deck.owner_id = ?1) AND deck.deleted_at IS NULL))
+        AND user_id IS ?1
+        "#,
+        user_id,
+        deck_id
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?;
+
+    Ok(DeckStats {
+        cards: cards.cards,
+        new: cards.new,
+        due: cards.due,
+        reviews: answers.reviews,
+        correct: answers.correct,
+    })
+}
+
+/// Returns the cards of a deck and the subdecks the user can see that the user has never
+/// reviewed or that are due again, leaving out suspended cards.
+///
+/// Cards borrowed by a filtered deck the user can see are only returned for that deck, where
+/// all of them are returned whether they are due or not.
+pub async fn due_cards_for_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: i64,
+) -> Result<Vec<Card>, sqlx::Error> {
+    let cards = sqlx::query_as!(
+        Card,
+        r#"
+        SELECT id, front, back 
+        FROM card 
+        WHERE (id IN (
+            SELECT card_id
+            FROM card_deck
+            JOIN deck_tree ON deck_tree.deck_id = card_deck.deck_id
+            JOIN deck ON deck.id = card_deck.deck_id
+            WHERE deck_tree.ancestor_id = ?1
+            AND (deck.id = ?1
+                OR (deck.owner_id IS NULL OR deck.owner_id = ?2) AND deck.deleted_at IS NULL))
+            AND id NOT IN (
+                SELECT card_id
+                FROM filtered_card
+                JOIN deck AS filtered ON filtered.id = filtered_card.deck_id
+                WHERE filtered.owner_id IS NULL OR filtered.owner_id = ?2)
+            AND id NOT IN (
+                SELECT card_id
+                FROM card_schedule
+                WHERE due_at > CURRENT_TIMESTAMP
+                AND user_id IS ?2)
+            OR id IN (SELECT card_id FROM filtered_card WHERE deck_id = ?1))
+        AND deleted_at IS NULL
+        AND NOT suspended
+        ORDER BY RANDOM();
+        "#,
+        deck_id,
+        user_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(cards)
+}
+
+/// Returns the cards of a deck and the subdecks the user can see that the user has never
+/// reviewed, leaving out suspended cards and the cards borrowed by another filtered deck the
+/// user can see.
+pub async fn new_cards_for_deck(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: i64,
+) -> Result<Vec<Card>, sqlx::Error> {
+    let cards = sqlx::query_as!(
+        Card,
+        r#"
+        SELECT id, front, back
+        FROM card
+        WHERE (id IN (
+            SELECT card_id
+            FROM card_deck
+            JOIN deck_tree ON deck_tree.deck_id = card_deck.deck_id
+            JOIN deck ON deck.id = card_deck.deck_id
+            WHERE deck_tree.ancestor_id = ?1
+            AND (deck.id = ?1
+                OR (deck.owner_id IS NULL OR deck.owner_id = ?2) AND deck.deleted_at IS NULL))
+            AND id NOT IN (
+                SELECT card_id
+                FROM filtered_card
+                JOIN deck AS filtered ON filtered.id = filtered_card.deck_id
+                WHERE filtered.owner_id IS NULL OR filtered.owner_id = ?2)
+            OR id IN (SELECT card_id FROM filtered_card WHERE deck_id = ?1))
+        AND id NOT IN (
+            SELECT card_id
+            FROM card_schedule
+            WHERE user_id IS ?2)
+        AND deleted_at IS NULL
+        AND NOT suspended
+        "#,
+        deck_id,
+        user_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(cards)
+}
+
+/// Returns the schedule of a card for a user along with the days elapsed since their last
+/// review of it.
+pub async fn query_card_schedule(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    card_id: i64,
+) -> Result<(CardSchedule, f64), sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+        SELECT
+            ease_factor,
+            interval_days,
+            repetitions,
+            stability,
+            difficulty,
+            julianday('now') - julianday(last_reviewed_at) AS "elapsed_days: f64"
+        FROM card_schedule
+        WHERE card_id = ?
+        AND user_id IS ?
+        "#,
+        card_id,
+        user_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(match res {
+        Some(res) => (
+            CardSchedule {
+                ease_factor: res.ease_factor,
+                interval_days: res.interval_days,
+                repetitions: res.repetitions,
+                stability: res.stability,
+                difficulty: res.difficulty,
+            },
+            res.elapsed_days.unwrap_or(0.0),
+        ),
+        None => (CardSchedule::default(), 0.0),
+    })
+}
+
+pub async fn update_card_schedule(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    card_id: i64,
+    schedule: &CardSchedule,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO card_schedule (
+            card_id, ease_factor, interval_days, repetitions, stability, difficulty,
+            due_at, last_reviewed_at, user_id
+        )
+        VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, datetime('now', '+' || ?3 || ' days'), CURRENT_TIMESTAMP, ?7
+        )
+        ON CONFLICT (IFNULL(user_id, 0), card_id) DO UPDATE SET
+            ease_factor = excluded.ease_factor,
+            interval_days = excluded.interval_days,
+            repetitions = excluded.repetitions,
+            stability = excluded.stability,
+            difficulty = excluded.difficulty,
+            due_at = excluded.due_at,
+            last_reviewed_at = excluded.last_reviewed_at
+        "#,
+        card_id,
+        schedule.ease_factor,
+        schedule.interval_days,
+        schedule.repetitions,
+        schedule.stability,
+        schedule.difficulty,
+        user_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn query_deck_scheduler(
+    tx: &mut Transaction<'_, Sqlite>,
+    deck_id: i64,
+) -> Result<SchedulerKind, sqlx::Error> {
+    let res = sqlx::query!("SELECT scheduler FROM deck WHERE id = ?", deck_id)
+        .fetch_one(tx.acquire().await?)
+        .await?;
+
+    Ok(SchedulerKind::from_str(&res.scheduler).unwrap_or_default())
+}
+
+pub async fn set_deck_scheduler(
+    tx: &mut Transaction<'_, Sqlite>,
+    deck_id: i64,
+    scheduler: SchedulerKind,
+) -> Result<(), sqlx::Error> {
+    log::debug!("Scheduling deck with id {} with {}", deck_id, scheduler);
+    let scheduler = scheduler.to_string();
+    sqlx::query!(
+        "UPDATE deck SET scheduler = ? WHERE id = ?",
+        scheduler,
+        deck_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn query_deck_review_mode(
+    tx: &mut Transaction<'_, Sqlite>,
+    deck_id: i64,
+) -> Result<ReviewMode, sqlx::Error> {
+    let res = sqlx::query!("SELECT review_mode FROM deck WHERE id = ?", deck_id)
+        .fetch_one(tx.acquire().await?)
+        .await?;
+
+    Ok(ReviewMode::from_str(&res.review_mode).unwrap_or_default())
+}
+
+pub async fn set_deck_review_mode(
+    tx: &mut Transaction<'_, Sqlite>,
+    deck_id: i64,
+    review_mode: ReviewMode,
+) -> Result<(), sqlx::Error> {
+    log::debug!("Reviewing deck with id {} in {} mode", deck_id, review_mode);
+    let review_mode = review_mode.to_string();
+    sqlx::query!(
+        "UPDATE deck SET review_mode = ? WHERE id = ?",
+        review_mode,
+        deck_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn query_deck_grader(
+    tx: &mut Transaction<'_, Sqlite>,
+    deck_id: i64,
+) -> Result<Grader, sqlx::Error> {
+    let res = sqlx::query!("SELECT grading FROM deck WHERE id = ?", deck_id)
+        .fetch_one(tx.acquire().await?)
+        .await?;
+
+    Ok(Grader::from_str(&res.grading).unwrap_or_default())
+}
+
+pub async fn set_deck_grader(
+    tx: &mut Transaction<'_, Sqlite>,
+    deck_id: i64,
+    grader: &Grader,
+) -> Result<(), sqlx::Error> {
+    log::debug!("Grading deck with id {} with {}", deck_id, grader);
+    let grading = grader.to_string();
+    sqlx::query!("UPDATE deck SET grading = ? WHERE id = ?", grading, deck_id)
+        .execute(tx.acquire().await?)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn create_session(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: i64,
+) -> Result<i64, sqlx::Error> {
+    let id = sqlx::query!(
+        "INSERT INTO session (user_id, deck_id) VALUES (?, ?) RETURNING id;",
+        user_id,
+        deck_id
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?
+    .id;
+
+    Ok(id)
+}
+
+/// Saves the cards a session starts with and the review mode it was asked for, so that it can
+/// be resumed as it was started.
+pub async fn save_session_queue(
+    tx: &mut Transaction<'_, Sqlite>,
+    session_id: i64,
+    cards: &[Card],
+    review_mode: Option<ReviewMode>,
+) -> Result<(), sqlx::Error> {
+    let review_mode = review_mode.map(|review_mode| review_mode.to_string());
+    sqlx::query!(
+        "UPDATE session SET review_mode = ? WHERE id = ?",
+        review_mode,
+        session_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+    for card in cards {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO session_card (session_id, card_id) VALUES (?, ?)",
+            session_id,
+            card.id
+        )
+        .execute(tx.acquire().await?)
+        .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn end_session(
+    tx: &mut Transaction<'_, Sqlite>,
+    session_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE session SET end_time = CURRENT_TIMESTAMP WHERE id = ?",
+        session_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn record_answer(
+    tx: &mut Transaction<'_, Sqlite>,
+    answer: &Answer,
+) -> Result<i64, sqlx::Error> {
+    let id = sqlx::query!(
+        r#"
+        INSERT INTO answer (
+            user_id, card_id, deck_id, session_id, answer, correct_answer, grade, elapsed_ms
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        RETURNING id;
+        "#,
+        answer.user_id,
+        answer.card_id,
+        answer.deck_id,
+        answer.session_id,
+        answer.answer,
+        answer.correct_answer,
+        answer.grade,
+        answer.elapsed_ms
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?
+    .id;
+
+    Ok(id)
+}
+
+/// Returns the most recent session of a user that was never ended.
+pub async fn query_open_session(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+) -> Result<Option<OpenSession>, sqlx::Error> {
+    let session = sqlx::query_as!(
+        OpenSession,
+        r#"
+        SELECT
+            session.id AS "id!",
+            deck.id AS "deck_id!",
+            deck.name AS deck_name,
+            CAST(session.start_time AS TEXT) AS "start_time!: String",
+            session.review_mode,
+            EXISTS (
+                SELECT 1 FROM session_card WHERE session_id = session.id
+            ) AS "has_queue!: bool"
+        FROM session
+        JOIN deck ON deck.id = session.deck_id
+        WHERE session.end_time IS NULL
+        AND session.user_id IS ?
+        AND deck.deleted_at IS NULL
+        ORDER BY session.start_time DESC, session.id DESC
+        LIMIT 1
+        "#,
+        user_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(session)
+}
+
+/// Returns the cards whose last answer in a session was wrong.
+pub async fn lapsed_cards_in_session(
+    tx: &mut Transaction<'_, Sqlite>,
+    session_id: i64,
+) -> Result<Vec<Card>, sqlx::Error> {
+    let cards = sqlx::query_as!(
+        Card,
+        r#"
+        SELECT card.id, card.front, card.back
+        FROM card
+        JOIN answer ON answer.card_id = card.id
+        WHERE answer.id IN (
+            SELECT MAX(id)
+            FROM answer
+            WHERE session_id = ?
+            GROUP BY card_id)
+        AND answer.grade = 1
+        AND card.deleted_at IS NULL
+        "#,
+        session_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(cards)
+}
+
+/// Returns the cards of the queue a session started with that are not remembered yet, which
+/// are the ones without an answer in it and the ones whose last answer in it was wrong.
+pub async fn remaining_cards_in_session(
+    tx: &mut Transaction<'_, Sqlite>,
+    session_id: i64,
+) -> Result<Vec<Card>, sqlx::Error> {
+    let cards = sqlx::query_as!(
+        Card,
+        r#"
+        SELECT card.id, card.front, card.back
+        FROM session_card
+        JOIN card ON card.id = session_card.card_id
+        WHERE session_card.session_id = ?1
+        AND card.deleted_at IS NULL
+        AND NOT card.suspended
+        AND NOT EXISTS (
+            SELECT 1
+            FROM answer
+            WHERE answer.id = (
+                SELECT MAX(id) FROM answer WHERE session_id = ?1 AND card_id = card.id)
+            AND answer.grade > 1)
+        "#,
+        session_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(cards)
+}
+
+/// Returns how many answers of a session were correct and incorrect.
+pub async fn query_session_summary(
+    tx: &mut Transaction<'_, Sqlite>,
+    session_id: i64,
+) -> Result<(i64, i64), sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE grade > 1) AS "correct!: i64",
+            COUNT(*) FILTER (WHERE grade = 1) AS "incorrect!: i64"
+        FROM answer
+        WHERE session_id = ?
+        "#,
+        session_id
+    )
+    .fetch_one(tx.acquire().await?)
+    .await?;
+
+    Ok((res.correct, res.incorrect))
+}
+
+pub async fn query_user_id(
+    tx: &mut Transaction<'_, Sqlite>,
+    username: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"SELECT id AS "id!" FROM user WHERE username = ?"#,
+        username
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(res.map(|res| res.id))
+}
+
+/// Returns the logged answers of a user and/or deck, or of everyone when neither is given.
+pub async fn query_review_logs(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: Option<i64>,
+) -> Result<Vec<ReviewLog>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT
+            card_id,
+            julianday(time) AS "reviewed_at!: f64",
+            trim(answer) = trim(correct_answer) AS "correct!: bool",
+            grade
+        FROM answer
+        WHERE (?1 IS NULL OR user_id = ?1)
+        AND (?2 IS NULL OR deck_id = ?2)
+        ORDER BY time
+        "#,
+        user_id,
+        deck_id
+    )
+    .fetch_all(tx.acquire().await?)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ReviewLog {
+            card_id: row.card_id,
+            reviewed_at: row.reviewed_at,
+            rating: match row.grade.and_then(Rating::from_grade) {
+                Some(rating) => rating,
+                None if row.correct => Rating::Good,
+                None => Rating::Again,
+            },
+        })
+        .collect())
+}
+
+pub async fn save_fsrs_weights(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: Option<i64>,
+    result: &optimizer::FitResult,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM fsrs_params WHERE user_id IS ? AND deck_id IS ?",
+        user_id,
+        deck_id
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    let weights = format_weights(&result.weights);
+    let review_count = result.review_count as i64;
+    sqlx::query!(
+        r#"
+        INSERT INTO fsrs_params (user_id, deck_id, weights, log_loss, review_count)
+        VALUES (?, ?, ?, ?, ?)
+        "#,
+        user_id,
+        deck_id,
+        weights,
+        result.log_loss,
+        review_count
+    )
+    .execute(tx.acquire().await?)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the most specific weights fitted for a user and deck.
+///
+/// Weights fitted for both win over weights for the user alone, which win over weights
+/// for the deck alone, which win over weights fitted on everyone's history.
+pub async fn query_fsrs_weights(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: i64,
+) -> Result<Option<[f64; WEIGHT_COUNT]>, sqlx::Error> {
+    let res = sqlx::query!(
+        r#"
+        SELECT weights
+        FROM fsrs_params
+        WHERE (user_id IS ?1 OR user_id IS NULL)
+        AND (deck_id = ?2 OR deck_id IS NULL)
+        ORDER BY user_id IS NULL, deck_id IS NULL, created_at DESC
+        LIMIT 1
+        "#,
+        user_id,
+        deck_id
+    )
+    .fetch_optional(tx.acquire().await?)
+    .await?;
+
+    Ok(res.and_then(|res| parse_weights(&res.weights)))
+}
+
+/// Fits FSRS weights to the answer history of a user and/or deck and stores them, returning
+/// the fit or `None` when there is not enough history.
+pub async fn optimize_fsrs(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: Option<i64>,
+) -> Result<Option<optimizer::FitResult>, sqlx::Error> {
+    let logs = query_review_logs(tx, user_id, deck_id).await?;
+    log::debug!("Optimizing FSRS weights on {} answers", logs.len());
+
+    let result = optimizer::fit(&logs);
+    if let Some(result) = &result {
+        save_fsrs_weights(tx, user_id, deck_id, result).await?;
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::{Scheduler, Sm2};
+    use dotenv::dotenv;
+
+    async fn create_transaction() -> Transaction<'static, Sqlite> {
+        dotenv().ok();
+        let database_url = dotenv::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let conn = sqlx::sqlite::SqlitePoolOptions::new()
+            .connect(&database_url)
+            .await
+            .unwrap();
+
+        conn.begin().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_create_card() {
+        let mut tx = create_transaction().await;
+
+        create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+
+        let card = sqlx::query!("SELECT front, back FROM card WHERE id = last_insert_rowid();")
+            .fetch_one(tx.acquire().await.unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(card.front, "front");
+        assert_eq!(card.back, "back");
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_card() {
+        let mut tx = create_transaction().await;
+
+        let record_id = sqlx::query!(
+            "INSERT INTO card (front, back) VALUES (?, ?) RETURNING id",
+            "front",
+            "back"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+
+        update_card(&mut tx, record_id, Some("new front".to_string()), None)
+            .await
+            .unwrap();
+
+        let card = sqlx::query!("SELECT front, back FROM card WHERE id = $1;", record_id)
+            .fetch_one(tx.acquire().await.unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(card.front, "new front");
+        assert_eq!(card.back, "back");
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_card() {
+        let mut tx = create_transaction().await;
+
+        sqlx::query!(
+            "INSERT INTO card (front, back) VALUES (?, ?)",
+            "front",
+            "back"
+        )
+        .execute(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+        delete_card(&mut tx, 1).await.unwrap();
+
+        // the card is only moved to the trash
+        let card = sqlx::query!("SELECT deleted_at FROM card WHERE id = 1;")
+            .fetch_one(tx.acquire().await.unwrap())
+            .await
+            .unwrap();
+
+        assert!(card.deleted_at.is_some());
+        assert!(!query_card_exists(&mut tx, 1).await.unwrap());
+        assert!(!delete_card(&mut tx, 1).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_missing_card() {
+        let mut tx = create_transaction().await;
+
+        let found = update_card(&mut tx, -1, Some("front".to_string()), None)
+            .await
+            .unwrap();
+
+        assert!(!found);
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_cards() {
+        let mut tx = create_transaction().await;
+
+        sqlx::query!(
+            "INSERT INTO card (front, back) VALUES (?, ?)",
+            "front",
+            "back"
+        )
+        .execute(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+        sqlx::query!(
+            "INSERT INTO card (front, back) VALUES (?, ?)",
+            "front2",
+            "back2"
+        )
+        .execute(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+        let fronts: Vec<String> = list_cards(&mut tx)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|card| card.front)
+            .collect();
+
+        assert!(fronts.contains(&"front".to_string()));
+        assert!(fronts.contains(&"front2".to_string()));
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_deck() {
+        let mut tx = create_transaction().await;
+
+        create_deck(
+            &mut tx,
+            "deck".to_string(),
+            Some("description".to_string()),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let deck =
+            sqlx::query!("SELECT name, description FROM deck WHERE id = last_insert_rowid();")
+                .fetch_one(tx.acquire().await.unwrap())
+                .await
+                .unwrap();
+
+        assert_eq!(deck.name, "deck");
+        assert_eq!(deck.description, Some("description".to_string()));
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_list_decks() {
+        let mut tx = create_transaction().await;
+
+        sqlx::query!(
+            "INSERT INTO deck (name, description) VALUES (?, ?)",
+            "deck",
+            "description"
+        )
+        .execute(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+        sqlx::query!(
+            "INSERT INTO deck (name, description) VALUES (?, ?)",
+            "deck2",
+            "description2"
+        )
+        .execute(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+
+        let names: Vec<String> = list_decks(&mut tx, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|deck| deck.name)
+            .collect();
+
+        assert!(names.contains(&"deck".to_string()));
+        assert!(names.contains(&"deck2".to_string()));
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unique_deck_names() {
+        let mut tx = create_transaction().await;
+
+        create_deck(
+            &mut tx,
+            "deck".to_string(),
+            Some("description".to_string()),
+            None,
+        )
+        .await
+        .unwrap();
+
+        let res = create_deck(
+            &mut tx,
+            "deck".to_string(),
+            Some("description".to_string()),
+            None,
+        )
+        .await;
+
+        assert!(res.is_err());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_due_cards_for_deck() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "due deck"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+        let new_card = create_card(&mut tx, "new".to_string(), "card".to_string())
+            .await
+            .unwrap();
+        let scheduled_card = create_card(&mut tx, "scheduled".to_string(), "card".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, new_card, deck_id).await.unwrap();
+        add_card_to_deck(&mut tx, scheduled_card, deck_id)
+            .await
+            .unwrap();
+
+        let schedule = Sm2.schedule(&CardSchedule::default(), Rating::Good, 0.0);
+        update_card_schedule(&mut tx, None, scheduled_card, &schedule)
+            .await
+            .unwrap();
+
+        let due = due_cards_for_deck(&mut tx, None, deck_id).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, Some(new_card));
+        let (stored, elapsed_days) = query_card_schedule(&mut tx, None, scheduled_card)
+            .await
+            .unwrap();
+        assert_eq!(stored, schedule);
+        assert!(elapsed_days < 1.0);
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_progress_is_per_user() {
+        let mut tx = create_transaction().await;
+
+        let alice = create_user(&mut tx, "alice".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let bob = create_user(&mut tx, "bob".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "shared deck"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+
+        let schedule = Sm2.schedule(&CardSchedule::default(), Rating::Good, 0.0);
+        update_card_schedule(&mut tx, Some(alice), card_id, &schedule)
+            .await
+            .unwrap();
+        let again = Sm2.schedule(&schedule, Rating::Good, 1.0);
+        update_card_schedule(&mut tx, Some(alice), card_id, &again)
+            .await
+            .unwrap();
+
+        assert!(due_cards_for_deck(&mut tx, Some(alice), deck_id)
+            .await
+            .unwrap()
+            .is_empty());
+        for user_id in [Some(bob), None] {
+            let due = due_cards_for_deck(&mut tx, user_id, deck_id).await.unwrap();
+            assert_eq!(due.len(), 1);
+            let new = new_cards_for_deck(&mut tx, user_id, deck_id).await.unwrap();
+            assert_eq!(new.len(), 1);
+        }
+        assert!(new_cards_for_deck(&mut tx, Some(alice), deck_id)
+            .await
+            .unwrap()
+            .is_empty());
+        let (stored, _) = query_card_schedule(&mut tx, Some(alice), card_id)
+            .await
+            .unwrap();
+        assert_eq!(stored, again);
+        let (stored, _) = query_card_schedule(&mut tx, Some(bob), card_id)
+            .await
+            .unwrap();
+        assert_eq!(stored, CardSchedule::default());
+
+        let stats = query_deck_stats(&mut tx, Some(alice), deck_id)
+            .await
+            .unwrap();
+        assert_eq!((stats.cards, stats.new, stats.due), (1, 0, 0));
+        let stats = query_deck_stats(&mut tx, Some(bob), deck_id).await.unwrap();
+        assert_eq!((stats.cards, stats.new, stats.due), (1, 1, 0));
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_private_decks() {
+        let mut tx = create_transaction().await;
+
+        let owner = create_user(&mut tx, "owner".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let other = create_user(&mut tx, "other".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let deck_id = create_deck(&mut tx, "private deck".to_string(), None, Some(owner))
+            .await
+            .unwrap();
+
+        assert!(query_deck_visible(&mut tx, Some(owner), deck_id)
+            .await
+            .unwrap());
+        assert!(!query_deck_visible(&mut tx, Some(other), deck_id)
+            .await
+            .unwrap());
+        assert!(!query_deck_visible(&mut tx, None, deck_id).await.unwrap());
+
+        assert!(!delete_deck(&mut tx, Some(other), deck_id, false)
+            .await
+            .unwrap());
+        assert!(query_deck_exists(&mut tx, deck_id).await.unwrap());
+        assert!(delete_deck(&mut tx, Some(owner), deck_id, false)
+            .await
+            .unwrap());
+        assert!(!query_deck_exists(&mut tx, deck_id).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_query_deck_id() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        // a deck named after the id of another deck does not shadow it
+        create_deck(&mut tx, deck_id.to_string(), None, None)
+            .await
+            .unwrap();
+
+        let by_name = query_deck_id(&mut tx, None, "spanish").await.unwrap();
+        assert_eq!(by_name, Some(deck_id));
+        let by_id = query_deck_id(&mut tx, None, &deck_id.to_string())
+            .await
+            .unwrap();
+        assert_eq!(by_id, Some(deck_id));
+        assert_eq!(query_deck_id(&mut tx, None, "french").await.unwrap(), None);
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_remove_card_from_deck() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "deck".to_string(), None, None)
+            .await
+            .unwrap();
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+
+        assert!(remove_card_from_deck(&mut tx, card_id, deck_id)
+            .await
+            .unwrap());
+        assert!(!remove_card_from_deck(&mut tx, card_id, deck_id)
+            .await
+            .unwrap());
+        assert!(query_card_exists(&mut tx, card_id).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_deck() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "deck".to_string(), None, None)
+            .await
+            .unwrap();
+        let other_deck_id = create_deck(&mut tx, "other deck".to_string(), None, None)
+            .await
+            .unwrap();
+        let mut card_ids = vec![];
+        for front in ["only in deck", "in both decks"] {
+            let card_id = create_card(&mut tx, front.to_string(), "back".to_string())
+                .await
+                .unwrap();
+            add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+            card_ids.push(card_id);
+        }
+        add_card_to_deck(&mut tx, card_ids[1], other_deck_id)
+            .await
+            .unwrap();
+
+        assert!(delete_deck(&mut tx, None, deck_id, true).await.unwrap());
+        assert!(!query_card_exists(&mut tx, card_ids[0]).await.unwrap());
+        assert!(query_card_exists(&mut tx, card_ids[1]).await.unwrap());
+        assert!(delete_deck(&mut tx, None, other_deck_id, false)
+            .await
+            .unwrap());
+        assert!(query_card_exists(&mut tx, card_ids[1]).await.unwrap());
+
+        // the links of the deleted decks go with them once they leave the trash
+        assert_eq!(purge_trash(&mut tx, None).await.unwrap(), 3);
+        let links = sqlx::query_scalar!(
+            "SELECT COUNT(*) FROM card_deck WHERE deck_id IN (?, ?)",
+            deck_id,
+            other_deck_id
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+        assert_eq!(links, 0);
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_deck_keeps_answers() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "answered deck".to_string(), None, None)
+            .await
+            .unwrap();
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+        let session_id = create_session(&mut tx, None, deck_id).await.unwrap();
+        record_answer(
+            &mut tx,
+            &Answer {
+                user_id: None,
+                card_id,
+                deck_id,
+                session_id,
+                answer: "back".to_string(),
+                correct_answer: "back".to_string(),
+                grade: Rating::Good.grade().into(),
+                elapsed_ms: 1500,
+            },
+        )
+        .await
+        .unwrap();
+
+        // the answers are the review history of the card, which outlives the deck
+        assert!(delete_deck(&mut tx, None, deck_id, false).await.unwrap());
+        purge_trash(&mut tx, None).await.unwrap();
+        let answers = sqlx::query!(
+            "SELECT deck_id, session_id FROM answer WHERE card_id = ?",
+            card_id
+        )
+        .fetch_all(tx.acquire().await.unwrap())
+        .await
+        .unwrap();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(
+            (answers[0].deck_id, answers[0].session_id),
+            (None, session_id)
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tags() {
+        let mut tx = create_transaction().await;
+
+        let card_id = create_card(&mut tx, "hola".to_string(), "hello".to_string())
+            .await
+            .unwrap();
+        let spanish = create_tag(&mut tx, "spanish".to_string(), None)
+            .await
+            .unwrap();
+        let greetings = create_tag(&mut tx, "greetings".to_string(), None)
+            .await
+            .unwrap();
+        assert!(create_tag(&mut tx, "spanish".to_string(), None)
+            .await
+            .is_err());
+        assert_eq!(
+            query_tag_id(&mut tx, "spanish").await.unwrap(),
+            Some(spanish)
+        );
+
+        assert!(tag_card(&mut tx, card_id, spanish).await.unwrap());
+        assert!(!tag_card(&mut tx, card_id, spanish).await.unwrap());
+        assert!(tag_card(&mut tx, card_id, greetings).await.unwrap());
+
+        let cards = list_cards(&mut tx).await.unwrap();
+        let card = cards.iter().find(|card| card.id == card_id).unwrap();
+        assert_eq!(card.tags, vec!["greetings", "spanish"]);
+        let cards = list_cards_for_tag(&mut tx, spanish).await.unwrap();
+        assert_eq!(cards.len(), 1);
+
+        let tags = list_tags(&mut tx).await.unwrap();
+        let counts: Vec<(&str, i64)> = tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.cards))
+            .collect();
+        assert_eq!(counts, vec![("greetings", 1), ("spanish", 1)]);
+
+        assert!(untag_card(&mut tx, card_id, greetings).await.unwrap());
+        assert!(!untag_card(&mut tx, card_id, greetings).await.unwrap());
+        assert!(
+            update_tag(&mut tx, spanish, Some("español".to_string()), None)
+                .await
+                .unwrap()
+        );
+        assert!(delete_tag(&mut tx, greetings).await.unwrap());
+        assert!(!delete_tag(&mut tx, greetings).await.unwrap());
+
+        // deleting a tag takes it off the cards
+        let cards = list_cards_for_tag(&mut tx, spanish).await.unwrap();
+        assert_eq!(cards[0].tags, vec!["español"]);
+        delete_tag(&mut tx, spanish).await.unwrap();
+        let cards = list_cards(&mut tx).await.unwrap();
+        let card = cards.iter().find(|card| card.id == card_id).unwrap();
+        assert!(card.tags.is_empty());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tag_hierarchy() {
+        let mut tx = create_transaction().await;
+
+        let card_id = create_card(&mut tx, "hablar".to_string(), "to speak".to_string())
+            .await
+            .unwrap();
+        let verbs = create_tag(&mut tx, "lang::spanish::verbs".to_string(), None)
+            .await
+            .unwrap();
+        tag_card(&mut tx, card_id, verbs).await.unwrap();
+
+        // the levels above a tag are created with it and include its cards
+        let lang = query_tag_id(&mut tx, "lang").await.unwrap().unwrap();
+        let spanish = query_tag_id(&mut tx, "lang::spanish")
+            .await
+            .unwrap()
+            .unwrap();
+        let cards = list_cards_for_tag(&mut tx, lang).await.unwrap();
+        assert_eq!(cards.len(), 1);
+        let tags = list_tags(&mut tx).await.unwrap();
+        let counts: Vec<(&str, i64)> = tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.cards))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("lang", 1),
+                ("lang::spanish", 1),
+                ("lang::spanish::verbs", 1)
+            ]
+        );
+
+        // a tag whose name only starts like another is not under it
+        let language = create_tag(&mut tx, "language".to_string(), None)
+            .await
+            .unwrap();
+        assert!(list_cards_for_tag(&mut tx, language)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // renaming a tag renames the tags under it
+        update_tag(&mut tx, lang, Some("languages".to_string()), None)
+            .await
+            .unwrap();
+        let cards = list_cards(&mut tx).await.unwrap();
+        let card = cards.iter().find(|card| card.id == card_id).unwrap();
+        assert_eq!(card.tags, vec!["languages::spanish::verbs"]);
+        assert_eq!(
+            query_tag_id(&mut tx, "languages::spanish").await.unwrap(),
+            Some(spanish)
+        );
+        assert_eq!(query_tag_id(&mut tx, "lang").await.unwrap(), None);
+
+        // deleting a tag deletes the tags under it
+        assert!(delete_tag(&mut tx, spanish).await.unwrap());
+        assert_eq!(
+            query_tag_id(&mut tx, "languages::spanish::verbs")
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            query_tag_id(&mut tx, "languages").await.unwrap(),
+            Some(lang)
+        );
+        assert!(list_cards_for_tag(&mut tx, lang).await.unwrap().is_empty());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subdecks() {
+        let mut tx = create_transaction().await;
+
+        let alice = create_user(&mut tx, "alice".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let verbs = create_deck(&mut tx, "languages::spanish::verbs".to_string(), None, None)
+            .await
+            .unwrap();
+        let french = create_deck(&mut tx, "languages::french".to_string(), None, Some(alice))
+            .await
+            .unwrap();
+        let languages = query_deck_id(&mut tx, None, "languages")
+            .await
+            .unwrap()
+            .unwrap();
+        let spanish = query_deck_id(&mut tx, None, "languages::spanish")
+            .await
+            .unwrap()
+            .unwrap();
+        for (front, deck_id) in [("hablar", verbs), ("hola", spanish), ("bonjour", french)] {
+            let card_id = create_card(&mut tx, front.to_string(), "back".to_string())
+                .await
+                .unwrap();
+            add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+            // a card in a deck and its subdeck only counts once
+            if front == "hola" {
+                add_card_to_deck(&mut tx, card_id, languages).await.unwrap();
+            }
+        }
+
+        // the private subdeck of alice only counts for her
+        let counts = |decks: Vec<ListDeck>| -> Vec<(String, i64)> {
+            decks
+                .into_iter()
+                .map(|deck| (deck.name, deck.cards))
+                .collect()
+        };
+        assert_eq!(
+            counts(list_decks(&mut tx, None).await.unwrap()),
+            vec![
+                ("languages".to_string(), 2),
+                ("languages::spanish".to_string(), 2),
+                ("languages::spanish::verbs".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            counts(list_decks(&mut tx, Some(alice)).await.unwrap())[..2],
+            [
+                ("languages".to_string(), 3),
+                ("languages::french".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            due_cards_for_deck(&mut tx, None, spanish)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        let stats = query_deck_stats(&mut tx, Some(alice), languages)
+            .await
+            .unwrap();
+        assert_eq!((stats.cards, stats.new), (3, 3));
+
+        // renaming a deck takes its subdecks along
+        update_deck(&mut tx, None, spanish, Some("español".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            query_deck_id(&mut tx, None, "español::verbs")
+                .await
+                .unwrap(),
+            Some(verbs)
+        );
+        assert_eq!(
+            query_deck_id(&mut tx, None, "languages").await.unwrap(),
+            Some(languages)
+        );
+
+        // a name that is taken leaves the subdecks where they were
+        assert!(
+            update_deck(&mut tx, None, spanish, Some("languages".to_string()), None)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            query_deck_id(&mut tx, None, "español::verbs")
+                .await
+                .unwrap(),
+            Some(verbs)
+        );
+
+        // deleting and restoring it takes them along too, keeping the card that is also in the
+        // parent
+        delete_deck(&mut tx, None, spanish, true).await.unwrap();
+        assert_eq!(list_decks(&mut tx, None).await.unwrap().len(), 1);
+        let due = due_cards_for_deck(&mut tx, None, languages).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].front, "hola");
+        assert!(restore_deck(&mut tx, None, spanish).await.unwrap());
+        assert!(query_deck_visible(&mut tx, None, verbs).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    async fn search(tx: &mut Transaction<'_, Sqlite>, search: &str) -> Vec<i64> {
+        let search: Search = search.parse().unwrap();
+        let cards = search_cards(tx, None, &search).await.unwrap();
+        cards.into_iter().map(|card| card.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_private_subdecks() {
+        let mut tx = create_transaction().await;
+
+        let alice = create_user(&mut tx, "alice".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let bob = create_user(&mut tx, "bob".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let languages = create_deck(&mut tx, "languages".to_string(), None, None)
+            .await
+            .unwrap();
+        let mine = create_deck(&mut tx, "languages::mine".to_string(), None, Some(bob))
+            .await
+            .unwrap();
+        create_deck(&mut tx, "private".to_string(), None, Some(alice))
+            .await
+            .unwrap();
+
+        // nobody else can put a deck under a private deck
+        for owner in [None, Some(bob)] {
+            assert!(matches!(
+                create_deck(&mut tx, "private::spanish".to_string(), None, owner).await,
+                Err(DeckError::PrivateAncestor(name)) if name == "private"
+            ));
+        }
+        assert!(matches!(
+            update_deck(
+                &mut tx,
+                Some(bob),
+                mine,
+                Some("private::mine".to_string()),
+                None
+            )
+            .await,
+            Err(DeckError::PrivateAncestor(_))
+        ));
+
+        // renaming and trashing a shared deck leaves the subdecks of other users alone
+        update_deck(
+            &mut tx,
+            Some(alice),
+            languages,
+            Some("idiomas".to_string()),
+            None,
+        )
+        .await
+        .unwrap();
+        let names: Vec<String> = list_decks(&mut tx, Some(bob))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|deck| deck.name)
+            .collect();
+        assert_eq!(names, vec!["idiomas", "languages::mine"]);
+        let mine = create_deck(&mut tx, "idiomas::mine".to_string(), None, Some(bob))
+            .await
+            .unwrap();
+        assert!(delete_deck(&mut tx, Some(alice), languages, false)
+            .await
+            .unwrap());
+        assert!(query_deck_visible(&mut tx, Some(bob), mine).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_cards() {
+        let mut tx = create_transaction().await;
+
+        let verbs = create_deck(&mut tx, "spanish::verbs".to_string(), None, None)
+            .await
+            .unwrap();
+        let mut ids = vec![];
+        for (front, back) in [
+            ("hablar", "to speak"),
+            ("comer", "to eat"),
+            ("hola", "hello"),
+        ] {
+            ids.push(
+                create_card(&mut tx, front.to_string(), back.to_string())
+                    .await
+                    .unwrap(),
+            );
+        }
+        let (hablar, comer, hola) = (ids[0], ids[1], ids[2]);
+        add_card_to_deck(&mut tx, hablar, verbs).await.unwrap();
+        add_card_to_deck(&mut tx, comer, verbs).await.unwrap();
+        let tag = create_tag(&mut tx, "grammar::verbs".to_string(), None)
+            .await
+            .unwrap();
+        tag_card(&mut tx, hablar, tag).await.unwrap();
+        let schedule = CardSchedule {
+            ease_factor: 1.5,
+            interval_days: 3,
+            ..CardSchedule::default()
+        };
+        update_card_schedule(&mut tx, None, comer, &schedule)
+            .await
+            .unwrap();
+        set_card_suspended(&mut tx, hola, true).await.unwrap();
+
+        assert_eq!(search(&mut tx, "deck:spanish").await, vec![hablar, comer]);
+        assert_eq!(search(&mut tx, "deck:spanish is:new").await, vec![hablar]);
+        assert_eq!(search(&mut tx, "tag:grammar").await, vec![hablar]);
+        assert_eq!(search(&mut tx, "front:*AR").await, vec![hablar]);
+        assert_eq!(
+            search(&mut tx, "\"to eat\" or is:suspended").await,
+            vec![comer, hola]
+        );
+        assert_eq!(
+            search(&mut tx, "prop:ease<2.0 prop:ivl=3").await,
+            vec![comer]
+        );
+        assert_eq!(search(&mut tx, "added:1 -deck:spanish").await, vec![hola]);
+
+        // suspended cards are left out of reviews
+        let deck_id = create_deck(&mut tx, "greetings".to_string(), None, None)
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, hola, deck_id).await.unwrap();
+        assert!(due_cards_for_deck(&mut tx, None, deck_id)
+            .await
+            .unwrap()
+            .is_empty());
+        set_card_suspended(&mut tx, hola, false).await.unwrap();
+        assert_eq!(
+            due_cards_for_deck(&mut tx, None, deck_id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_full_text_search() {
+        let mut tx = create_transaction().await;
+
+        let adios = create_card(&mut tx, "adiós".to_string(), "goodbye".to_string())
+            .await
+            .unwrap();
+        let hasta = create_card(
+            &mut tx,
+            "hasta luego".to_string(),
+            "see you later, a softer goodbye than adiós".to_string(),
+        )
+        .await
+        .unwrap();
+
+        // accents and case are ignored, and the closest match comes first
+        let search: Search = "ADIOS".parse().unwrap();
+        let cards = search_cards(&mut tx, None, &search).await.unwrap();
+        let ids: Vec<i64> = cards.iter().map(|card| card.id).collect();
+        assert_eq!(ids, vec![adios, hasta]);
+        assert_eq!(cards[0].snippet.as_deref(), Some("[adiós]"));
+        assert_eq!(
+            cards[1].snippet.as_deref(),
+            Some("see you later, a softer goodbye than [adiós]")
+        );
+        let search: Search = "lueg* or is:new".parse().unwrap();
+        let cards = search_cards(&mut tx, None, &search).await.unwrap();
+        assert_eq!(cards[0].id, hasta);
+        assert_eq!(cards[0].snippet.as_deref(), Some("hasta [luego]"));
+
+        // the index follows the cards as they change
+        update_card(&mut tx, adios, Some("chao".to_string()), None)
+            .await
+            .unwrap();
+        delete_card(&mut tx, hasta).await.unwrap();
+        for (text, expected) in [("adiós", vec![]), ("chao", vec![adios])] {
+            let search: Search = text.parse().unwrap();
+            let cards = search_cards(&mut tx, None, &search).await.unwrap();
+            let ids: Vec<i64> = cards.iter().map(|card| card.id).collect();
+            assert_eq!(ids, expected);
+        }
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trash() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "deck".to_string(), None, None)
+            .await
+            .unwrap();
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+
+        assert!(delete_deck(&mut tx, None, deck_id, true).await.unwrap());
+        assert!(list_decks(&mut tx, None).await.unwrap().is_empty());
+        assert!(list_cards(&mut tx).await.unwrap().is_empty());
+        assert!(
+            !update_card(&mut tx, card_id, Some("front".to_string()), None)
+                .await
+                .unwrap()
+        );
+
+        let trash = list_trash(&mut tx, None).await.unwrap();
+        let trash: Vec<(&str, i64)> = trash
+            .iter()
+            .map(|item| (item.kind.as_str(), item.id))
+            .collect();
+        assert_eq!(trash, vec![("card", card_id), ("deck", deck_id)]);
+
+        // restoring the deck brings back the card that was deleted with it
+        assert!(restore_deck(&mut tx, None, deck_id).await.unwrap());
+        assert!(!restore_deck(&mut tx, None, deck_id).await.unwrap());
+        assert_eq!(
+            list_cards_for_deck(&mut tx, deck_id).await.unwrap().len(),
+            1
+        );
+        assert!(list_trash(&mut tx, None).await.unwrap().is_empty());
+
+        assert!(delete_card(&mut tx, card_id).await.unwrap());
+        assert!(list_cards_for_deck(&mut tx, deck_id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(restore_card(&mut tx, card_id).await.unwrap());
+        assert!(query_card_exists(&mut tx, card_id).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_restore_deck_cards() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "deck".to_string(), None, None)
+            .await
+            .unwrap();
+        let mut cards = vec![];
+        for front in ["kept", "deleted before"] {
+            let card_id = create_card(&mut tx, front.to_string(), "back".to_string())
+                .await
+                .unwrap();
+            add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+            cards.push(card_id);
+        }
+
+        // a card deleted on its own stays in the trash, even when deleted at the same time
+        delete_card(&mut tx, cards[1]).await.unwrap();
+        delete_deck(&mut tx, None, deck_id, true).await.unwrap();
+        assert!(restore_deck(&mut tx, None, deck_id).await.unwrap());
+        assert!(query_card_exists(&mut tx, cards[0]).await.unwrap());
+        assert!(!query_card_exists(&mut tx, cards[1]).await.unwrap());
+
+        // and a card restored on its own is not tied to the deck anymore
+        delete_deck(&mut tx, None, deck_id, true).await.unwrap();
+        assert!(restore_card(&mut tx, cards[0]).await.unwrap());
+        delete_card(&mut tx, cards[0]).await.unwrap();
+        assert!(restore_deck(&mut tx, None, deck_id).await.unwrap());
+        assert!(!query_card_exists(&mut tx, cards[0]).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_purge_trash_per_user() {
+        let mut tx = create_transaction().await;
+
+        let alice = create_user(&mut tx, "alice".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let bob = create_user(&mut tx, "bob".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let mut cards = vec![];
+        for (name, owner) in [("alice", alice), ("bob", bob)] {
+            let deck_id = create_deck(&mut tx, name.to_string(), None, Some(owner))
+                .await
+                .unwrap();
+            let card_id = create_card(&mut tx, name.to_string(), "back".to_string())
+                .await
+                .unwrap();
+            add_card_to_deck(&mut tx, card_id, deck_id).await.unwrap();
+            delete_deck(&mut tx, Some(owner), deck_id, true)
+                .await
+                .unwrap();
+            cards.push(card_id);
+        }
+
+        // bob only sees and purges his own deck and card
+        let trash = list_trash(&mut tx, Some(bob)).await.unwrap();
+        assert_eq!(trash.len(), 2);
+        assert_eq!(purge_trash(&mut tx, Some(bob)).await.unwrap(), 2);
+        assert_eq!(list_trash(&mut tx, Some(alice)).await.unwrap().len(), 2);
+        assert!(restore_card(&mut tx, cards[0]).await.unwrap());
+        assert!(!restore_card(&mut tx, cards[1]).await.unwrap());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_update_deck_description() {
+        let mut tx = create_transaction().await;
+
+        let id = create_deck(
+            &mut tx,
+            "described".to_string(),
+            Some("old".to_string()),
+            None,
+        )
+        .await
+        .unwrap();
+        let description = |decks: Vec<ListDeck>| {
+            decks
+                .into_iter()
+                .find(|deck| deck.id == id)
+                .unwrap()
+                .description
+        };
+
+        // leaving the description out keeps it
+        update_deck(&mut tx, None, id, Some("renamed".to_string()), None)
+            .await
+            .unwrap();
+        let decks = list_decks(&mut tx, None).await.unwrap();
+        assert_eq!(description(decks), Some("old".to_string()));
+
+        update_deck(&mut tx, None, id, None, Some(None))
+            .await
+            .unwrap();
+        let decks = list_decks(&mut tx, None).await.unwrap();
+        assert_eq!(description(decks), None);
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trashed_deck_names() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = create_deck(&mut tx, "trashed".to_string(), None, None)
+            .await
+            .unwrap();
+        let other_id = create_deck(&mut tx, "other".to_string(), None, None)
+            .await
+            .unwrap();
+        assert!(delete_deck(&mut tx, None, deck_id, false).await.unwrap());
 
-        update_card(&mut tx, record_id, Some("new front".to_string()), None)
+        // the trashed deck keeps its name until it is restored or purged
+        let res = create_deck(&mut tx, "trashed".to_string(), None, None).await;
+        assert!(matches!(res, Err(DeckError::InTrash(name)) if name == "trashed"));
+        let res = update_deck(&mut tx, None, other_id, Some("trashed".to_string()), None).await;
+        assert!(matches!(res, Err(DeckError::InTrash(name)) if name == "trashed"));
+
+        purge_trash(&mut tx, None).await.unwrap();
+        assert!(
+            update_deck(&mut tx, None, other_id, Some("trashed".to_string()), None)
+                .await
+                .unwrap()
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trashed_deck_ancestors() {
+        let mut tx = create_transaction().await;
+
+        let spanish = create_deck(&mut tx, "trashed::spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let parent = query_deck_id(&mut tx, None, "trashed")
             .await
+            .unwrap()
             .unwrap();
+        assert_eq!(
+            query_trashed_deck_name(&mut tx, "trashed::french")
+                .await
+                .unwrap(),
+            None
+        );
 
-        let card = sqlx::query!("SELECT front, back FROM card WHERE id = $1;", record_id)
-            .fetch_one(tx.acquire().await.unwrap())
+        // the deck in the trash holds its name and the names under it
+        delete_deck(&mut tx, None, parent, false).await.unwrap();
+        for name in ["trashed", "trashed::french", "trashed::spanish::verbs"] {
+            assert_eq!(
+                query_trashed_deck_name(&mut tx, name)
+                    .await
+                    .unwrap()
+                    .as_deref(),
+                Some("trashed")
+            );
+        }
+        assert_eq!(
+            query_trashed_deck_name(&mut tx, "trashed deck")
+                .await
+                .unwrap(),
+            None
+        );
+
+        // restoring a subdeck brings back the decks above it
+        assert!(restore_deck(&mut tx, None, spanish).await.unwrap());
+        assert!(query_deck_visible(&mut tx, None, parent).await.unwrap());
+        assert_eq!(
+            query_trashed_deck_name(&mut tx, "trashed::french")
+                .await
+                .unwrap(),
+            None
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_purge_expired_trash() {
+        let mut tx = create_transaction().await;
+
+        let mut card_ids = vec![];
+        for days in [40, 10] {
+            let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+                .await
+                .unwrap();
+            delete_card(&mut tx, card_id).await.unwrap();
+            sqlx::query!(
+                "UPDATE card SET deleted_at = datetime('now', '-' || ? || ' days') WHERE id = ?",
+                days,
+                card_id
+            )
+            .execute(tx.acquire().await.unwrap())
             .await
             .unwrap();
+            card_ids.push(card_id);
+        }
 
-        assert_eq!(card.front, "new front");
-        assert_eq!(card.back, "back");
+        assert_eq!(purge_expired_trash(&mut tx, 30).await.unwrap(), 1);
+        assert!(!restore_card(&mut tx, card_ids[0]).await.unwrap());
+        assert!(restore_card(&mut tx, card_ids[1]).await.unwrap());
 
         tx.rollback().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_delete_card() {
+    async fn test_deck_scheduler() {
         let mut tx = create_transaction().await;
 
-        sqlx::query!(
-            "INSERT INTO card (front, back) VALUES (?, ?)",
-            "front",
-            "back"
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "scheduled deck"
         )
-        .execute(tx.acquire().await.unwrap())
+        .fetch_one(tx.acquire().await.unwrap())
         .await
-        .unwrap();
+        .unwrap()
+        .id;
 
-        delete_card(&mut tx, 1).await.unwrap();
+        assert_eq!(
+            query_deck_scheduler(&mut tx, deck_id).await.unwrap(),
+            SchedulerKind::Sm2
+        );
 
-        let card = sqlx::query!("SELECT front, back FROM card WHERE id = 1;")
-            .fetch_optional(tx.acquire().await.unwrap())
+        set_deck_scheduler(&mut tx, deck_id, SchedulerKind::Fsrs)
             .await
             .unwrap();
 
-        assert_eq!(card.is_none(), true);
+        assert_eq!(
+            query_deck_scheduler(&mut tx, deck_id).await.unwrap(),
+            SchedulerKind::Fsrs
+        );
 
         tx.rollback().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_list_cards() {
+    async fn test_fsrs_weights_precedence() {
         let mut tx = create_transaction().await;
 
-        sqlx::query!(
-            "INSERT INTO card (front, back) VALUES (?, ?)",
-            "front",
-            "back"
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "optimized deck"
         )
-        .execute(tx.acquire().await.unwrap())
+        .fetch_one(tx.acquire().await.unwrap())
         .await
-        .unwrap();
+        .unwrap()
+        .id;
+        let user_id = create_user(&mut tx, "optimized user".to_string(), "pw".to_string())
+            .await
+            .unwrap();
 
-        sqlx::query!(
-            "INSERT INTO card (front, back) VALUES (?, ?)",
-            "front2",
-            "back2"
+        let mut result = optimizer::FitResult {
+            weights: [1.0; WEIGHT_COUNT],
+            initial_log_loss: 0.5,
+            log_loss: 0.4,
+            review_count: 20,
+        };
+        save_fsrs_weights(&mut tx, None, Some(deck_id), &result)
+            .await
+            .unwrap();
+        assert_eq!(
+            query_fsrs_weights(&mut tx, Some(user_id), deck_id)
+                .await
+                .unwrap(),
+            Some([1.0; WEIGHT_COUNT])
+        );
+
+        result.weights = [2.0; WEIGHT_COUNT];
+        save_fsrs_weights(&mut tx, Some(user_id), None, &result)
+            .await
+            .unwrap();
+        assert_eq!(
+            query_fsrs_weights(&mut tx, Some(user_id), deck_id)
+                .await
+                .unwrap(),
+            Some([2.0; WEIGHT_COUNT])
+        );
+        assert_eq!(
+            query_fsrs_weights(&mut tx, None, deck_id).await.unwrap(),
+            Some([1.0; WEIGHT_COUNT])
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deck_review_mode() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "flipped deck"
         )
-        .execute(tx.acquire().await.unwrap())
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+
+        assert_eq!(
+            query_deck_review_mode(&mut tx, deck_id).await.unwrap(),
+            ReviewMode::Typed
+        );
+
+        set_deck_review_mode(&mut tx, deck_id, ReviewMode::Flip)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            query_deck_review_mode(&mut tx, deck_id).await.unwrap(),
+            ReviewMode::Flip
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deck_grader() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "graded deck"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+
+        assert_eq!(
+            query_deck_grader(&mut tx, deck_id).await.unwrap(),
+            Grader::default()
+        );
+
+        let grader: Grader = "casefold,levenshtein=0.7".parse().unwrap();
+        set_deck_grader(&mut tx, deck_id, &grader).await.unwrap();
+
+        assert_eq!(query_deck_grader(&mut tx, deck_id).await.unwrap(), grader);
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_session_answers() {
+        let mut tx = create_transaction().await;
+
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "session deck"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+
+        let session_id = create_session(&mut tx, None, deck_id).await.unwrap();
+        for (given, grade) in [("bak", Rating::Again), ("back", Rating::Good)] {
+            record_answer(
+                &mut tx,
+                &Answer {
+                    user_id: None,
+                    card_id,
+                    deck_id,
+                    session_id,
+                    answer: given.to_string(),
+                    correct_answer: "back".to_string(),
+                    grade: grade.grade().into(),
+                    elapsed_ms: 1500,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        end_session(&mut tx, session_id).await.unwrap();
+
+        let session = sqlx::query!(
+            "SELECT deck_id, end_time IS NOT NULL AS ended FROM session WHERE id = ?",
+            session_id
+        )
+        .fetch_one(tx.acquire().await.unwrap())
         .await
         .unwrap();
+        assert_eq!(session.deck_id, Some(deck_id));
+        assert_eq!(session.ended, 1);
 
-        list_cards(&mut tx).await.unwrap();
+        let ratings: Vec<Rating> = query_review_logs(&mut tx, None, Some(deck_id))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|log| log.rating)
+            .collect();
+        assert_eq!(ratings, vec![Rating::Again, Rating::Good]);
 
         tx.rollback().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_create_deck() {
+    async fn test_open_session_queue() {
         let mut tx = create_transaction().await;
 
-        create_deck(&mut tx, "deck".to_string(), Some("description".to_string()))
+        let deck_id = sqlx::query!(
+            "INSERT INTO deck (name) VALUES (?) RETURNING id",
+            "interrupted deck"
+        )
+        .fetch_one(tx.acquire().await.unwrap())
+        .await
+        .unwrap()
+        .id;
+        let user_id = create_user(&mut tx, "interrupted".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let remembered = create_card(&mut tx, "a".to_string(), "a".to_string())
+            .await
+            .unwrap();
+        let forgotten = create_card(&mut tx, "b".to_string(), "b".to_string())
+            .await
+            .unwrap();
+        let unanswered = create_card(&mut tx, "c".to_string(), "c".to_string())
             .await
             .unwrap();
 
-        let deck =
-            sqlx::query!("SELECT name, description FROM deck WHERE id = last_insert_rowid();")
-                .fetch_one(tx.acquire().await.unwrap())
-                .await
-                .unwrap();
+        let session_id = create_session(&mut tx, Some(user_id), deck_id)
+            .await
+            .unwrap();
+        let queue: Vec<Card> = [remembered, forgotten, unanswered]
+            .into_iter()
+            .map(|id| Card {
+                id: Some(id),
+                front: String::new(),
+                back: String::new(),
+            })
+            .collect();
+        save_session_queue(&mut tx, session_id, &queue, Some(ReviewMode::Flip))
+            .await
+            .unwrap();
+        for (card_id, grade) in [(forgotten, 3), (remembered, 3), (forgotten, 1)] {
+            record_answer(
+                &mut tx,
+                &Answer {
+                    user_id: Some(user_id),
+                    card_id,
+                    deck_id,
+                    session_id,
+                    answer: String::new(),
+                    correct_answer: String::new(),
+                    grade,
+                    elapsed_ms: 0,
+                },
+            )
+            .await
+            .unwrap();
+        }
 
-        assert_eq!(deck.name, "deck");
-        assert_eq!(deck.description, Some("description".to_string()));
+        let open = query_open_session(&mut tx, Some(user_id))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(open.id, session_id);
+        assert_eq!(open.deck_name, "interrupted deck");
+        assert!(open.has_queue);
+        assert_eq!(open.review_mode.as_deref(), Some("flip"));
+
+        // the rest of the queue is what is left to remember
+        let mut remaining: Vec<Option<i64>> = remaining_cards_in_session(&mut tx, session_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|card| card.id)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec![Some(forgotten), Some(unanswered)]);
+
+        let lapsed = lapsed_cards_in_session(&mut tx, session_id).await.unwrap();
+        assert_eq!(lapsed.len(), 1);
+        assert_eq!(lapsed[0].id, Some(forgotten));
+        assert_eq!(
+            query_session_summary(&mut tx, session_id).await.unwrap(),
+            (2, 1)
+        );
+
+        end_session(&mut tx, session_id).await.unwrap();
+        assert!(query_open_session(&mut tx, Some(user_id))
+            .await
+            .unwrap()
+            .is_none());
 
         tx.rollback().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_list_decks() {
+    async fn test_create_user() {
         let mut tx = create_transaction().await;
 
-        sqlx::query!(
-            "INSERT INTO deck (name, description) VALUES (?, ?)",
-            "deck",
-            "description"
+        let id = create_user(&mut tx, "learner".to_string(), "secret".to_string())
+            .await
+            .unwrap();
+
+        let credentials = query_user_credentials(&mut tx, "learner")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(credentials.id, id);
+        assert_ne!(credentials.password_hash, "secret");
+        assert!(bcrypt::verify("secret", &credentials.password_hash).unwrap());
+        assert!(query_user_credentials(&mut tx, "nobody")
+            .await
+            .unwrap()
+            .is_none());
+
+        let res = create_user(&mut tx, "learner".to_string(), "other".to_string()).await;
+        assert!(res.is_err());
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filtered_decks() {
+        let mut tx = create_transaction().await;
+
+        let spanish = create_deck(&mut tx, "spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let mut cards = vec![];
+        for front in ["uno", "dos", "tres"] {
+            let card_id = create_card(&mut tx, front.to_string(), "back".to_string())
+                .await
+                .unwrap();
+            add_card_to_deck(&mut tx, card_id, spanish).await.unwrap();
+            cards.push(card_id);
+        }
+        let cram = create_filtered_deck(
+            &mut tx,
+            "cram".to_string(),
+            None,
+            "deck:spanish".to_string(),
+            Some(2),
+            None,
         )
-        .execute(tx.acquire().await.unwrap())
         .await
         .unwrap();
+        assert_eq!(query_deck_search(&mut tx, spanish).await.unwrap(), None);
+        assert_eq!(
+            query_deck_search(&mut tx, cram).await.unwrap().as_deref(),
+            Some("deck:spanish")
+        );
 
-        sqlx::query!(
-            "INSERT INTO deck (name, description) VALUES (?, ?)",
-            "deck2",
-            "description2"
+        // borrowed cards stay in their home deck but are only reviewed in the filtered deck
+        assert_eq!(rebuild_filtered_deck(&mut tx, cram).await.unwrap(), 2);
+        assert_eq!(
+            list_cards_for_deck(&mut tx, spanish).await.unwrap().len(),
+            3
+        );
+        assert_eq!(list_cards_for_deck(&mut tx, cram).await.unwrap().len(), 2);
+        // due cards come in a random order
+        let due = |cards: Vec<Card>| -> Vec<i64> {
+            let mut ids: Vec<i64> = cards.into_iter().map(|card| card.id.unwrap()).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            due(due_cards_for_deck(&mut tx, None, spanish).await.unwrap()),
+            vec![cards[2]]
+        );
+        assert_eq!(
+            due(due_cards_for_deck(&mut tx, None, cram).await.unwrap()),
+            cards[..2]
+        );
+
+        // a card is borrowed by one filtered deck at a time
+        let rest = create_filtered_deck(
+            &mut tx,
+            "rest".to_string(),
+            None,
+            "deck:spanish".to_string(),
+            None,
+            None,
         )
-        .execute(tx.acquire().await.unwrap())
         .await
         .unwrap();
+        assert_eq!(rebuild_filtered_deck(&mut tx, rest).await.unwrap(), 1);
 
-        list_decks(&mut tx).await.unwrap();
+        // returning, emptying and trashing give the cards back
+        return_filtered_card(&mut tx, cards[0], cram).await.unwrap();
+        assert_eq!(
+            due(due_cards_for_deck(&mut tx, None, spanish).await.unwrap()),
+            vec![cards[0]]
+        );
+        assert_eq!(empty_filtered_deck(&mut tx, cram).await.unwrap(), 1);
+        delete_deck(&mut tx, None, rest, false).await.unwrap();
+        assert_eq!(
+            due_cards_for_deck(&mut tx, None, spanish)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(list_cards_for_deck(&mut tx, rest).await.unwrap().len(), 0);
 
         tx.rollback().await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_unique_deck_names() {
+    async fn test_filtered_decks_per_owner() {
         let mut tx = create_transaction().await;
 
-        create_deck(&mut tx, "deck".to_string(), Some("description".to_string()))
+        let alice = create_user(&mut tx, "alice".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let bob = create_user(&mut tx, "bob".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let shared = create_deck(&mut tx, "shared".to_string(), None, None)
+            .await
+            .unwrap();
+        let card_id = create_card(&mut tx, "front".to_string(), "back".to_string())
+            .await
+            .unwrap();
+        add_card_to_deck(&mut tx, card_id, shared).await.unwrap();
+        let mut filtered = vec![];
+        for (name, owner) in [("alice cram", alice), ("bob cram", bob)] {
+            let id = create_filtered_deck(
+                &mut tx,
+                name.to_string(),
+                None,
+                "deck:shared".to_string(),
+                None,
+                Some(owner),
+            )
+            .await
+            .unwrap();
+            filtered.push(id);
+        }
+
+        // the private decks of alice and bob both borrow the card
+        assert_eq!(
+            rebuild_filtered_deck(&mut tx, filtered[0]).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            rebuild_filtered_deck(&mut tx, filtered[1]).await.unwrap(),
+            1
+        );
+        return_filtered_card(&mut tx, card_id, filtered[1])
             .await
             .unwrap();
 
-        let res = create_deck(&mut tx, "deck".to_string(), Some("description".to_string())).await;
+        // which only takes it out of the shared deck for alice
+        assert!(due_cards_for_deck(&mut tx, Some(alice), shared)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(new_cards_for_deck(&mut tx, Some(alice), shared)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            due_cards_for_deck(&mut tx, Some(bob), shared)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            new_cards_for_deck(&mut tx, None, shared)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        tx.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_filtered_deck_of_failed_cards() {
+        let mut tx = create_transaction().await;
+
+        let alice = create_user(&mut tx, "alice".to_string(), "pw".to_string())
+            .await
+            .unwrap();
+        let spanish = create_deck(&mut tx, "spanish".to_string(), None, None)
+            .await
+            .unwrap();
+        let mut cards = vec![];
+        for front in ["uno", "dos"] {
+            let card_id = create_card(&mut tx, front.to_string(), "back".to_string())
+                .await
+                .unwrap();
+            add_card_to_deck(&mut tx, card_id, spanish).await.unwrap();
+            cards.push(card_id);
+        }
+        let session_id = create_session(&mut tx, Some(alice), spanish).await.unwrap();
+        for (card_id, grade) in [(cards[0], Rating::Again), (cards[1], Rating::Good)] {
+            record_answer(
+                &mut tx,
+                &Answer {
+                    user_id: Some(alice),
+                    card_id,
+                    deck_id: spanish,
+                    session_id,
+                    answer: "bak".to_string(),
+                    correct_answer: "back".to_string(),
+                    grade: grade.grade().into(),
+                    elapsed_ms: 1500,
+                },
+            )
+            .await
+            .unwrap();
+        }
 
-        assert_eq!(res.is_err(), true);
+        // the search of a private deck looks at the answers of its owner
+        let failed = create_filtered_deck(
+            &mut tx,
+            "failed".to_string(),
+            None,
+            "rated:3:1".to_string(),
+            None,
+            Some(alice),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rebuild_filtered_deck(&mut tx, failed).await.unwrap(), 1);
+        let borrowed: Vec<i64> = list_cards_for_deck(&mut tx, failed)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|card| card.id)
+            .collect();
+        assert_eq!(borrowed, vec![cards[0]]);
+
+        // while nobody failed a card for a shared deck
+        let shared = create_filtered_deck(
+            &mut tx,
+            "shared failed".to_string(),
+            None,
+            "rated:3:1".to_string(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rebuild_filtered_deck(&mut tx, shared).await.unwrap(), 0);
 
         tx.rollback().await.unwrap();
     }
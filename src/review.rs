@@ -0,0 +1,364 @@
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use crate::diff::print_diff;
+use crate::grading::Grader;
+use crate::models::{Answer, Card, User};
+use crate::queries::{
+    create_session, due_cards_for_deck, end_session, lapsed_cards_in_session, list_cards_for_tag,
+    new_cards_for_deck, query_card_schedule, query_deck_grader, query_deck_review_mode,
+    query_deck_scheduler, query_fsrs_weights, query_open_session, query_session_summary,
+    record_answer, remaining_cards_in_session, return_filtered_card, save_session_queue,
+    update_card_schedule,
+};
+use crate::scheduler::{Fsrs, Rating, Scheduler, SchedulerKind};
+use clap::ValueEnum;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+
+/// How the back of a card is checked during a review.
+#[derive(EnumIter, EnumString, Display, ValueEnum, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum ReviewMode {
+    /// The back is typed in and compared with the card.
+    #[default]
+    Typed,
+    /// The back is revealed on a keypress and the user grades themselves.
+    Flip,
+}
+
+/// Narrows down which cards of a deck are reviewed and how.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReviewOptions {
+    /// At most this many cards are reviewed.
+    pub limit: Option<usize>,
+    /// At most this many of them are cards that were never reviewed.
+    pub new: Option<usize>,
+    /// Reviews in this mode instead of the one of the deck.
+    pub mode: Option<ReviewMode>,
+    /// Only the cards with this tag or a tag under it are reviewed.
+    pub tag: Option<i64>,
+}
+
+/// Builds the scheduler of a deck, using the FSRS weights fitted for the user when there are any.
+pub async fn deck_scheduler(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: Option<i64>,
+    deck_id: i64,
+) -> Result<Box<dyn Scheduler + Send + Sync>, sqlx::Error> {
+    Ok(match query_deck_scheduler(tx, deck_id).await? {
+        SchedulerKind::Fsrs => match query_fsrs_weights(tx, user_id, deck_id).await? {
+            Some(weights) => Box::new(Fsrs::new(weights)),
+            None => Box::new(Fsrs::default()),
+        },
+        kind => kind.scheduler(),
+    })
+}
+
+/// Reads a line from stdin, returning `None` once stdin is closed so that a review stops
+/// instead of answering every remaining card with nothing.
+fn read_line() -> Result<Option<String>, io::Error> {
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(input))
+}
+
+/// Asks for the back of a card and grades it, returning the typed answer and its rating.
+fn review_typed(card: &Card, grader: &Grader) -> Result<Option<(String, Rating)>, io::Error> {
+    println!("What is the back?");
+    let Some(input) = read_line()? else {
+        return Ok(None);
+    };
+
+    let score = grader.score(&input, &card.back);
+    let rating = grader.rating(score);
+    match rating {
+        Rating::Again => println!("Incorrect!"),
+        Rating::Hard => println!("Almost! ({:.0}% similar)", score * 100.0),
+        _ => println!("Correct!"),
+    }
+    let input = input.trim().to_string();
+    if rating != Rating::Good {
+        print_diff(&input, &card.back);
+    }
+    Ok(Some((input, rating)))
+}
+
+fn review_flip(card: &Card) -> Result<Option<Rating>, io::Error> {
+    print!("Press enter to show the back...");
+    io::stdout().flush()?;
+    if read_line()?.is_none() {
+        return Ok(None);
+    }
+    println!("Back: {}", card.back);
+
+    loop {
+        println!("How well did you remember it?");
+        for (index, rating) in Rating::iter().enumerate() {
+            println!("{}. {}", index + 1, rating);
+        }
+        let Some(input) = read_line()? else {
+            return Ok(None);
+        };
+        let rating = input
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .and_then(Rating::from_grade);
+        match rating {
+            Some(rating) => return Ok(Some(rating)),
+            None => println!("Please enter a number between 1 and 4"),
+        }
+    }
+}
+
+/// Where a review shows its cards and gets its answers from.
+pub trait ReviewFrontend {
+    /// Called when a session starts, with the number of cards in its queue.
+    fn start(&mut self, session_id: i64, cards: usize) -> Result<(), io::Error>;
+
+    /// Called instead of `start` when there is nothing to review.
+    fn nothing_due(&mut self) -> Result<(), io::Error>;
+
+    /// Shows a card and returns the typed answer, if any, and how well it was recalled.
+    ///
+    /// Returns `None` when the reviewer has gone, such as when the input is closed, which ends
+    /// the session with the cards answered so far.
+    fn ask(
+        &mut self,
+        card: &Card,
+        mode: ReviewMode,
+        grader: &Grader,
+    ) -> Result<Option<(String, Rating)>, io::Error>;
+
+    /// Called once the answer to a card is saved, with the days until it is due again.
+    fn scheduled(
+        &mut self,
+        card: &Card,
+        rating: Rating,
+        interval_days: i64,
+    ) -> Result<(), io::Error>;
+
+    /// Called when the queue is empty, with the results of the session.
+    fn finish(&mut self, correct: i64, incorrect: i64) -> Result<(), io::Error>;
+}
+
+/// Reviews with prompts in the terminal, reading the answers from stdin.
+pub struct Terminal;
+
+impl ReviewFrontend for Terminal {
+    fn start(&mut self, _session_id: i64, _cards: usize) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn nothing_due(&mut self) -> Result<(), io::Error> {
+        println!("No cards are due in this deck");
+        Ok(())
+    }
+
+    fn ask(
+        &mut self,
+        card: &Card,
+        mode: ReviewMode,
+        grader: &Grader,
+    ) -> Result<Option<(String, Rating)>, io::Error> {
+        println!("Front: {}", card.front);
+        match mode {
+            ReviewMode::Typed => review_typed(card, grader),
+            ReviewMode::Flip => Ok(review_flip(card)?.map(|rating| (String::new(), rating))),
+        }
+    }
+
+    fn scheduled(
+        &mut self,
+        _card: &Card,
+        _rating: Rating,
+        interval_days: i64,
+    ) -> Result<(), io::Error> {
+        println!("Next review in {} day(s)", interval_days);
+        Ok(())
+    }
+
+    fn finish(&mut self, correct: i64, incorrect: i64) -> Result<(), io::Error> {
+        println!("You got {} correct and {} incorrect", correct, incorrect);
+        Ok(())
+    }
+}
+
+/// Reviews the due cards of a deck and its subdecks in a new session, or only those with a
+/// tag or its subtags.
+///
+/// Every answer is committed as soon as it is given, so that an interrupted session can be
+/// resumed with `resume_interrupted_session`.
+pub async fn review_deck(
+    pool: &SqlitePool,
+    user: &User,
+    id: i64,
+    options: &ReviewOptions,
+    frontend: &mut dyn ReviewFrontend,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut cards = due_cards_for_deck(&mut tx, user.id(), id).await?;
+    if let Some(tag_id) = options.tag {
+        let tagged: HashSet<Option<i64>> = list_cards_for_tag(&mut tx, tag_id)
+            .await?
+            .into_iter()
+            .map(|card| Some(card.id))
+            .collect();
+        cards.retain(|card| tagged.contains(&card.id));
+    }
+    if let Some(new) = options.new {
+        let new_cards: HashSet<Option<i64>> = new_cards_for_deck(&mut tx, user.id(), id)
+            .await?
+            .into_iter()
+            .map(|card| card.id)
+            .collect();
+        let mut kept = 0;
+        cards.retain(|card| {
+            if !new_cards.contains(&card.id) {
+                return true;
+            }
+            kept += 1;
+            kept <= new
+        });
+    }
+    if let Some(limit) = options.limit {
+        cards.truncate(limit);
+    }
+
+    if cards.is_empty() {
+        frontend.nothing_due()?;
+        return Ok(());
+    }
+
+    let session_id = create_session(&mut tx, user.id(), id).await?;
+    save_session_queue(&mut tx, session_id, &cards, options.mode).await?;
+    tx.commit().await?;
+
+    run_session(pool, user, id, session_id, cards, options.mode, frontend).await
+}
+
+/// Offers to continue the last unfinished session of a user, if there is one.
+///
+/// A declined session is closed so that it is not offered again; its answers are kept.
+pub async fn resume_interrupted_session(pool: &SqlitePool, user: &User) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let session = match query_open_session(&mut tx, user.id()).await? {
+        Some(session) => session,
+        None => return Ok(()),
+    };
+
+    print!(
+        "You have an unfinished review of deck {} started at {}. Continue it? (y/n) ",
+        session.deck_name, session.start_time
+    );
+    io::stdout().flush()?;
+    // with stdin closed the session is left for next time
+    let Some(answer) = read_line()? else {
+        return Ok(());
+    };
+    if !answer.trim().eq_ignore_ascii_case("y") {
+        end_session(&mut tx, session.id).await?;
+        tx.commit().await?;
+        return Ok(());
+    }
+
+    let cards = if session.has_queue {
+        remaining_cards_in_session(&mut tx, session.id).await?
+    } else {
+        // without a saved queue, cards answered correctly are no longer due, so what is left
+        // is the cards that are still due and the ones whose last answer was wrong
+        let mut cards = lapsed_cards_in_session(&mut tx, session.id).await?;
+        for card in due_cards_for_deck(&mut tx, user.id(), session.deck_id).await? {
+            if !cards.iter().any(|lapsed| lapsed.id == card.id) {
+                cards.push(card);
+            }
+        }
+        cards
+    };
+    tx.commit().await?;
+    let review_mode = session
+        .review_mode
+        .and_then(|review_mode| review_mode.parse::<ReviewMode>().ok());
+
+    run_session(
+        pool,
+        user,
+        session.deck_id,
+        session.id,
+        cards,
+        review_mode,
+        &mut Terminal,
+    )
+    .await
+}
+
+/// Asks for each card of the queue until they are all remembered, then ends the session.
+async fn run_session(
+    pool: &SqlitePool,
+    user: &User,
+    deck_id: i64,
+    session_id: i64,
+    mut cards: Vec<Card>,
+    review_mode: Option<ReviewMode>,
+    frontend: &mut dyn ReviewFrontend,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let scheduler = deck_scheduler(&mut tx, user.id(), deck_id).await?;
+    let review_mode = match review_mode {
+        Some(review_mode) => review_mode,
+        None => query_deck_review_mode(&mut tx, deck_id).await?,
+    };
+    let grader = query_deck_grader(&mut tx, deck_id).await?;
+    tx.commit().await?;
+    frontend.start(session_id, cards.len())?;
+
+    while let Some(card) = cards.pop() {
+        let card_id = card.id.expect("cards from the database have an id");
+        let started = Instant::now();
+        let Some((given, rating)) = frontend.ask(&card, review_mode, &grader)? else {
+            break;
+        };
+
+        let mut tx = pool.begin().await?;
+        record_answer(
+            &mut tx,
+            &Answer {
+                user_id: user.id(),
+                card_id,
+                deck_id,
+                session_id,
+                answer: given,
+                correct_answer: card.back.clone(),
+                grade: rating.grade().into(),
+                elapsed_ms: started.elapsed().as_millis() as i64,
+            },
+        )
+        .await?;
+
+        let (schedule, elapsed_days) = query_card_schedule(&mut tx, user.id(), card_id).await?;
+        let schedule = scheduler.schedule(&schedule, rating, elapsed_days);
+        update_card_schedule(&mut tx, user.id(), card_id, &schedule).await?;
+        // a card remembered in a filtered deck goes back to its home decks
+        if !rating.is_lapse() {
+            return_filtered_card(&mut tx, card_id, deck_id).await?;
+        }
+        tx.commit().await?;
+        frontend.scheduled(&card, rating, schedule.interval_days)?;
+
+        if rating.is_lapse() {
+            cards.insert(0, card);
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+    end_session(&mut tx, session_id).await?;
+    let (correct, incorrect) = query_session_summary(&mut tx, session_id).await?;
+    tx.commit().await?;
+    frontend.finish(correct, incorrect)?;
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use super::{Rating, Scheduler};
+use crate::models::CardSchedule;
+
+/// The number of weights of the FSRS-4.5 model.
+pub const WEIGHT_COUNT: usize = 17;
+
+/// The FSRS-4.5 weights fitted on the open spaced repetition dataset.
+pub const DEFAULT_WEIGHTS: [f64; WEIGHT_COUNT] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072,
+    0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0;
+
+/// The Free Spaced Repetition Scheduler (FSRS-4.5).
+///
+/// Every card carries a memory state made of a stability, the number of days after which
+/// recall drops to 90%, and a difficulty between 1 and 10.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fsrs {
+    pub weights: [f64; WEIGHT_COUNT],
+    pub desired_retention: f64,
+}
+
+impl Default for Fsrs {
+    fn default() -> Self {
+        Self::new(DEFAULT_WEIGHTS)
+    }
+}
+
+impl Fsrs {
+    pub fn new(weights: [f64; WEIGHT_COUNT]) -> Self {
+        Self {
+            weights,
+            desired_retention: 0.9,
+        }
+    }
+
+    /// The probability of recalling a card `elapsed_days` after its last review.
+    pub fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+        (1.0 + FACTOR * elapsed_days / stability).powf(DECAY)
+    }
+
+    pub fn initial_stability(&self, rating: Rating) -> f64 {
+        self.weights[usize::from(rating.grade()) - 1].max(0.1)
+    }
+
+    pub fn initial_difficulty(&self, rating: Rating) -> f64 {
+        let w = &self.weights;
+        (w[4] - (f64::from(rating.grade()) - 3.0) * w[5]).clamp(1.0, 10.0)
+    }
+
+    pub fn next_difficulty(&self, difficulty: f64, rating: Rating) -> f64 {
+        let w = &self.weights;
+        let next = difficulty - w[6] * (f64::from(rating.grade()) - 3.0);
+        (w[7] * self.initial_difficulty(Rating::Good) + (1.0 - w[7]) * next).clamp(1.0, 10.0)
+    }
+
+    pub fn next_stability(
+        &self,
+        difficulty: f64,
+        stability: f64,
+        retrievability: f64,
+        rating: Rating,
+    ) -> f64 {
+        let w = &self.weights;
+        if rating.is_lapse() {
+            let forget = w[11]
+                * difficulty.powf(-w[12])
+                * ((stability + 1.0).powf(w[13]) - 1.0)
+                * (w[14] * (1.0 - retrievability)).exp();
+            return forget.clamp(0.1, stability);
+        }
+
+        let hard_penalty = if rating == Rating::Hard { w[15] } else { 1.0 };
+        let easy_bonus = if rating == Rating::Easy { w[16] } else { 1.0 };
+        stability
+            * (1.0
+                + w[8].exp()
+                    * (11.0 - difficulty)
+                    * stability.powf(-w[9])
+                    * ((w[10] * (1.0 - retrievability)).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus)
+    }
+
+    /// The memory state after a review, given the memory state before it, if any.
+    pub fn next_memory_state(
+        &self,
+        memory: Option<(f64, f64)>,
+        rating: Rating,
+        elapsed_days: f64,
+    ) -> (f64, f64) {
+        match memory {
+            None => (
+                self.initial_stability(rating),
+                self.initial_difficulty(rating),
+            ),
+            Some((stability, difficulty)) => {
+                let retrievability = Self::retrievability(elapsed_days.max(0.0), stability);
+                (
+                    self.next_stability(difficulty, stability, retrievability, rating),
+                    self.next_difficulty(difficulty, rating),
+                )
+            }
+        }
+    }
+
+    /// The number of days until recall drops to the desired retention.
+    pub fn next_interval(&self, stability: f64) -> i64 {
+        let interval = stability / FACTOR * (self.desired_retention.powf(1.0 / DECAY) - 1.0);
+        (interval.round() as i64).max(1)
+    }
+}
+
+impl Scheduler for Fsrs {
+    fn schedule(&self, card: &CardSchedule, rating: Rating, elapsed_days: f64) -> CardSchedule {
+        let memory = card.stability.zip(card.difficulty);
+        let (stability, difficulty) = self.next_memory_state(memory, rating, elapsed_days);
+        let interval_days = if rating.is_lapse() {
+            1
+        } else {
+            self.next_interval(stability)
+        };
+        let repetitions = if rating.is_lapse() {
+            0
+        } else {
+            card.repetitions + 1
+        };
+
+        CardSchedule {
+            interval_days,
+            repetitions,
+            stability: Some(stability),
+            difficulty: Some(difficulty),
+            ..card.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fsrs_new_card() {
+        let fsrs = Fsrs::default();
+        let card = fsrs.schedule(&CardSchedule::default(), Rating::Good, 0.0);
+
+        assert_eq!(card.stability, Some(DEFAULT_WEIGHTS[2]));
+        assert_eq!(card.difficulty, Some(fsrs.initial_difficulty(Rating::Good)));
+        assert_eq!(card.interval_days, 4);
+        assert_eq!(card.repetitions, 1);
+    }
+
+    #[test]
+    fn test_fsrs_intervals_grow_and_lapses_shrink() {
+        let fsrs = Fsrs::default();
+        let first = fsrs.schedule(&CardSchedule::default(), Rating::Good, 0.0);
+        let second = fsrs.schedule(&first, Rating::Good, first.interval_days as f64);
+        assert!(second.interval_days > first.interval_days);
+
+        let easy = fsrs.schedule(&first, Rating::Easy, first.interval_days as f64);
+        assert!(easy.interval_days > second.interval_days);
+
+        let lapsed = fsrs.schedule(&second, Rating::Again, second.interval_days as f64);
+        assert_eq!(lapsed.interval_days, 1);
+        assert!(lapsed.stability < second.stability);
+        assert!(lapsed.difficulty > second.difficulty);
+    }
+
+    #[test]
+    fn test_retrievability_at_stability() {
+        assert!((Fsrs::retrievability(10.0, 10.0) - 0.9).abs() < 1e-9);
+        assert_eq!(Fsrs::retrievability(0.0, 10.0), 1.0);
+    }
+}
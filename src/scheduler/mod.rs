@@ -0,0 +1,94 @@
+mod fsrs;
+pub mod optimizer;
+mod sm2;
+
+pub use self::fsrs::{Fsrs, WEIGHT_COUNT};
+pub use self::sm2::Sm2;
+
+use crate::models::CardSchedule;
+
+use strum::{Display, EnumIter, EnumString};
+
+/// How well a card was recalled, from forgotten to effortless.
+#[derive(EnumIter, Display, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Rating {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Rating {
+    /// The rating as the 1-4 grade used by FSRS.
+    pub fn grade(self) -> u8 {
+        match self {
+            Rating::Again => 1,
+            Rating::Hard => 2,
+            Rating::Good => 3,
+            Rating::Easy => 4,
+        }
+    }
+
+    pub fn from_grade(grade: i64) -> Option<Self> {
+        match grade {
+            1 => Some(Rating::Again),
+            2 => Some(Rating::Hard),
+            3 => Some(Rating::Good),
+            4 => Some(Rating::Easy),
+            _ => None,
+        }
+    }
+
+    pub fn is_lapse(self) -> bool {
+        self == Rating::Again
+    }
+}
+
+/// A spaced repetition algorithm that decides when a card is due again.
+pub trait Scheduler {
+    /// Computes the schedule of a card after it was reviewed.
+    ///
+    /// `elapsed_days` is the time since the previous review, or 0 for a new card.
+    fn schedule(&self, card: &CardSchedule, rating: Rating, elapsed_days: f64) -> CardSchedule;
+}
+
+/// The schedulers a deck can choose from.
+#[derive(EnumIter, EnumString, Display, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[strum(serialize_all = "lowercase")]
+pub enum SchedulerKind {
+    #[default]
+    Sm2,
+    Fsrs,
+}
+
+impl SchedulerKind {
+    pub fn scheduler(self) -> Box<dyn Scheduler + Send + Sync> {
+        match self {
+            SchedulerKind::Sm2 => Box::new(Sm2),
+            SchedulerKind::Fsrs => Box::new(Fsrs::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn test_scheduler_kind_round_trip() {
+        for kind in [SchedulerKind::Sm2, SchedulerKind::Fsrs] {
+            assert_eq!(SchedulerKind::from_str(&kind.to_string()), Ok(kind));
+        }
+        assert!(SchedulerKind::from_str("anki").is_err());
+    }
+
+    #[test]
+    fn test_rating_grade_round_trip() {
+        for rating in Rating::iter() {
+            assert_eq!(Rating::from_grade(rating.grade().into()), Some(rating));
+        }
+        assert_eq!(Rating::from_grade(0), None);
+    }
+}
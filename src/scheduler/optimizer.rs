@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+
+use super::fsrs::{Fsrs, DEFAULT_WEIGHTS, WEIGHT_COUNT};
+use super::Rating;
+
+/// Fewer reviews than this cannot tell the weights apart from noise.
+pub const MIN_REVIEWS: usize = 16;
+
+const EPOCHS: usize = 200;
+const LEARNING_RATE: f64 = 0.02;
+const STEP: f64 = 1e-4;
+
+/// The range every weight is kept in so the model stays well defined.
+const BOUNDS: [(f64, f64); WEIGHT_COUNT] = [
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (1.0, 10.0),
+    (0.1, 5.0),
+    (0.1, 5.0),
+    (0.0, 0.5),
+    (0.0, 3.0),
+    (0.1, 0.8),
+    (0.01, 2.5),
+    (0.5, 5.0),
+    (0.01, 0.2),
+    (0.01, 0.9),
+    (0.01, 2.0),
+    (0.0, 1.0),
+    (1.0, 4.0),
+];
+
+/// A logged answer to a card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewLog {
+    pub card_id: i64,
+    /// When the answer was given, as a julian day.
+    pub reviewed_at: f64,
+    pub rating: Rating,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitResult {
+    pub weights: [f64; WEIGHT_COUNT],
+    pub initial_log_loss: f64,
+    pub log_loss: f64,
+    pub review_count: usize,
+}
+
+/// One review of a card, with the days since the previous review of the same card.
+type Step = (f64, Rating);
+
+/// Groups the logs per card in chronological order, keeping the first answer of each day.
+fn card_histories(logs: &[ReviewLog]) -> Vec<Vec<Step>> {
+    let mut per_card: BTreeMap<i64, Vec<&ReviewLog>> = BTreeMap::new();
+    for log in logs {
+        per_card.entry(log.card_id).or_default().push(log);
+    }
+
+    per_card
+        .into_values()
+        .map(|mut logs| {
+            logs.sort_by(|a, b| a.reviewed_at.total_cmp(&b.reviewed_at));
+            let mut history: Vec<Step> = vec![];
+            let mut last_review: Option<f64> = None;
+            for log in logs {
+                match last_review {
+                    None => history.push((0.0, log.rating)),
+                    Some(last) if log.reviewed_at - last >= 1.0 => {
+                        history.push((log.reviewed_at - last, log.rating))
+                    }
+                    Some(_) => continue,
+                }
+                last_review = Some(log.reviewed_at);
+            }
+            history
+        })
+        .collect()
+}
+
+/// The mean binary cross entropy between predicted recall and the actual answers.
+///
+/// The first review of a card has nothing to predict from, so only later reviews count.
+fn log_loss(weights: &[f64; WEIGHT_COUNT], histories: &[Vec<Step>]) -> f64 {
+    let fsrs = Fsrs::new(*weights);
+    let mut loss = 0.0;
+    let mut count = 0;
+
+    for history in histories {
+        let mut memory: Option<(f64, f64)> = None;
+        for &(elapsed_days, rating) in history {
+            if let Some((stability, _)) = memory {
+                let recall = Fsrs::retrievability(elapsed_days, stability).clamp(1e-6, 1.0 - 1e-6);
+                let recalled = if rating.is_lapse() { 0.0 } else { 1.0 };
+                loss -= recalled * recall.ln() + (1.0 - recalled) * (1.0 - recall).ln();
+                count += 1;
+            }
+            memory = Some(fsrs.next_memory_state(memory, rating, elapsed_days));
+        }
+    }
+
+    if count == 0 {
+        0.0
+    } else {
+        loss / count as f64
+    }
+}
+
+/// The number of reviews that contribute to the loss.
+fn review_count(histories: &[Vec<Step>]) -> usize {
+    histories
+        .iter()
+        .map(|history| history.len().saturating_sub(1))
+        .sum()
+}
+
+/// Fits FSRS weights to a review history by gradient descent on the log loss.
+///
+/// Gradients are estimated with central differences and applied with Adam, starting from
+/// the default weights. Returns `None` when the history is too short to fit anything.
+pub fn fit(logs: &[ReviewLog]) -> Option<FitResult> {
+    let histories = card_histories(logs);
+    let review_count = review_count(&histories);
+    if review_count < MIN_REVIEWS {
+        return None;
+    }
+
+    let mut weights = DEFAULT_WEIGHTS;
+    let initial_log_loss = log_loss(&weights, &histories);
+    let mut best = (initial_log_loss, weights);
+
+    let (beta1, beta2, epsilon) = (0.9, 0.999, 1e-8);
+    let mut m = [0.0; WEIGHT_COUNT];
+    let mut v = [0.0; WEIGHT_COUNT];
+
+    for epoch in 1..=EPOCHS {
+        let mut gradient = [0.0; WEIGHT_COUNT];
+        for i in 0..WEIGHT_COUNT {
+            let (mut up, mut down) = (weights, weights);
+            up[i] += STEP;
+            down[i] -= STEP;
+            gradient[i] = (log_loss(&up, &histories) - log_loss(&down, &histories)) / (2.0 * STEP);
+        }
+
+        for i in 0..WEIGHT_COUNT {
+            m[i] = beta1 * m[i] + (1.0 - beta1) * gradient[i];
+            v[i] = beta2 * v[i] + (1.0 - beta2) * gradient[i] * gradient[i];
+            let m_hat = m[i] / (1.0 - beta1.powi(epoch as i32));
+            let v_hat = v[i] / (1.0 - beta2.powi(epoch as i32));
+            let (low, high) = BOUNDS[i];
+            weights[i] =
+                (weights[i] - LEARNING_RATE * m_hat / (v_hat.sqrt() + epsilon)).clamp(low, high);
+        }
+
+        let loss = log_loss(&weights, &histories);
+        if loss < best.0 {
+            best = (loss, weights);
+        }
+    }
+
+    Some(FitResult {
+        weights: best.1,
+        initial_log_loss,
+        log_loss: best.0,
+        review_count,
+    })
+}
+
+/// Formats weights for storage as comma separated values.
+pub fn format_weights(weights: &[f64; WEIGHT_COUNT]) -> String {
+    weights
+        .iter()
+        .map(|w| format!("{:.4}", w))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses weights stored by `format_weights`.
+pub fn parse_weights(weights: &str) -> Option<[f64; WEIGHT_COUNT]> {
+    let weights: Vec<f64> = weights
+        .split(',')
+        .map(|w| w.trim().parse().ok())
+        .collect::<Option<_>>()?;
+
+    weights.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cards that are always remembered, reviewed at growing intervals.
+    fn easy_history() -> Vec<ReviewLog> {
+        let mut logs = vec![];
+        for card_id in 0..8 {
+            let mut day = 2_460_000.0;
+            for interval in [0.0, 3.0, 10.0, 30.0, 90.0] {
+                day += interval;
+                logs.push(ReviewLog {
+                    card_id,
+                    reviewed_at: day,
+                    rating: Rating::Good,
+                });
+            }
+        }
+        logs
+    }
+
+    #[test]
+    fn test_fit_reduces_log_loss() {
+        let result = fit(&easy_history()).unwrap();
+
+        assert_eq!(result.review_count, 32);
+        assert!(result.log_loss < result.initial_log_loss);
+    }
+
+    #[test]
+    fn test_fit_needs_enough_reviews() {
+        assert_eq!(fit(&easy_history()[..5]), None);
+    }
+
+    #[test]
+    fn test_same_day_answers_are_ignored() {
+        let logs = [0.0, 0.1, 0.2, 2.0].map(|offset| ReviewLog {
+            card_id: 1,
+            reviewed_at: 2_460_000.0 + offset,
+            rating: Rating::Again,
+        });
+
+        assert_eq!(
+            card_histories(&logs),
+            vec![vec![(0.0, Rating::Again), (2.0, Rating::Again)]]
+        );
+    }
+
+    #[test]
+    fn test_weights_round_trip() {
+        let weights = parse_weights(&format_weights(&DEFAULT_WEIGHTS)).unwrap();
+
+        assert_eq!(weights, DEFAULT_WEIGHTS);
+        assert_eq!(parse_weights("1,2,3"), None);
+    }
+}
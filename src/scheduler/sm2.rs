@@ -0,0 +1,101 @@
+use super::{Rating, Scheduler};
+use crate::models::CardSchedule;
+
+/// The lowest ease factor SM-2 allows, so that hard cards still grow their interval.
+const MIN_EASE_FACTOR: f64 = 1.3;
+
+/// The classic SuperMemo 2 algorithm.
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+    fn schedule(&self, card: &CardSchedule, rating: Rating, _elapsed_days: f64) -> CardSchedule {
+        let quality = match rating {
+            Rating::Again => 1,
+            Rating::Hard => 3,
+            Rating::Good => 4,
+            Rating::Easy => 5,
+        };
+        sm2(card, quality)
+    }
+}
+
+/// Computes the next schedule of a card with the SM-2 algorithm.
+///
+/// `quality` is the SM-2 response quality from 0 (blackout) to 5 (perfect recall).
+/// Anything below 3 counts as a lapse and restarts the repetitions.
+pub fn sm2(schedule: &CardSchedule, quality: u8) -> CardSchedule {
+    let quality = quality.min(5);
+    let q = f64::from(5 - quality);
+    let ease_factor = (schedule.ease_factor + (0.1 - q * (0.08 + q * 0.02))).max(MIN_EASE_FACTOR);
+
+    if quality < 3 {
+        return CardSchedule {
+            ease_factor,
+            interval_days: 1,
+            repetitions: 0,
+            ..schedule.clone()
+        };
+    }
+
+    let interval_days = match schedule.repetitions {
+        0 => 1,
+        1 => 6,
+        _ => (schedule.interval_days as f64 * schedule.ease_factor).round() as i64,
+    };
+
+    CardSchedule {
+        ease_factor,
+        interval_days,
+        repetitions: schedule.repetitions + 1,
+        ..schedule.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sm2_first_reviews() {
+        let first = sm2(&CardSchedule::default(), 4);
+        assert_eq!(first.interval_days, 1);
+        assert_eq!(first.repetitions, 1);
+        assert_eq!(first.ease_factor, 2.5);
+
+        let second = sm2(&first, 4);
+        assert_eq!(second.interval_days, 6);
+        assert_eq!(second.repetitions, 2);
+
+        let third = sm2(&second, 5);
+        assert_eq!(third.interval_days, 15);
+        assert_eq!(third.repetitions, 3);
+        assert!(third.ease_factor > 2.5);
+    }
+
+    #[test]
+    fn test_sm2_lapse_resets_repetitions() {
+        let schedule = CardSchedule {
+            ease_factor: 2.5,
+            interval_days: 15,
+            repetitions: 3,
+            ..CardSchedule::default()
+        };
+
+        let lapsed = Sm2.schedule(&schedule, Rating::Again, 15.0);
+        assert_eq!(lapsed.interval_days, 1);
+        assert_eq!(lapsed.repetitions, 0);
+        assert!(lapsed.ease_factor < 2.5);
+    }
+
+    #[test]
+    fn test_sm2_ease_factor_floor() {
+        let schedule = CardSchedule {
+            ease_factor: MIN_EASE_FACTOR,
+            interval_days: 1,
+            repetitions: 0,
+            ..CardSchedule::default()
+        };
+
+        assert_eq!(sm2(&schedule, 0).ease_factor, MIN_EASE_FACTOR);
+    }
+}
@@ -0,0 +1,637 @@
+use std::str::FromStr;
+
+use sqlx::{QueryBuilder, Sqlite};
+
+/// A state a card can be in for a user.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CardState {
+    /// never reviewed
+    New,
+    /// reviewed before and due again
+    Due,
+    /// left out of reviews
+    Suspended,
+}
+
+/// A number kept in the schedule of a card.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Property {
+    Ease,
+    Interval,
+    Repetitions,
+    /// the days until the card is due, negative once it is overdue
+    Due,
+    Stability,
+    Difficulty,
+}
+
+impl Property {
+    fn column(self) -> &'static str {
+        match self {
+            Property::Ease => "ease_factor",
+            Property::Interval => "interval_days",
+            Property::Repetitions => "repetitions",
+            Property::Due => "julianday(due_at) - julianday('now')",
+            Property::Stability => "stability",
+            Property::Difficulty => "difficulty",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn operator(self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+}
+
+/// A parsed card search, such as `deck:Spanish tag:verbs is:due front:*ar -is:suspended`.
+///
+/// Terms next to each other must all match, `or` between them lets either match, `-` in
+/// front of a term negates it and parentheses group terms. A `*` in a value matches any
+/// text, and text is matched without regard to ASCII case.
+///
+/// Plain text is looked up in the full-text index of the cards, so it matches whole words
+/// regardless of case and accents, or the start of a word when it ends with `*`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Search {
+    /// words anywhere on the front or the back
+    Text(String),
+    /// the whole front, which can use `*` to match part of it
+    Front(String),
+    /// the whole back, which can use `*` to match part of it
+    Back(String),
+    /// cards in a deck or one of its subdecks
+    Deck(String),
+    /// cards with a tag or one of the tags under it
+    Tag(String),
+    Is(CardState),
+    /// cards created in the last so many days
+    Added(u32),
+    /// cards answered in the last so many days, only with the given 1-4 grade if there is one
+    Rated(u32, Option<u8>),
+    Property(Property, Comparison, f64),
+    Not(Box<Search>),
+    And(Vec<Search>),
+    Or(Vec<Search>),
+}
+
+impl Search {
+    /// Appends a condition on the `card` table that holds for the cards matching the search,
+    /// using the schedules and decks of `user_id`.
+    pub fn push_condition(&self, builder: &mut QueryBuilder<'_, Sqlite>, user_id: Option<i64>) {
+        match self {
+            Search::Text(text) if full_text_phrase(text).is_some() => {
+                builder
+                    .push("card.id IN (SELECT rowid FROM card_fts WHERE card_fts MATCH ")
+                    .push_bind(full_text_phrase(text))
+                    .push(")");
+            }
+            // the index cannot look inside words, nor for text without any
+            Search::Text(text) => {
+                let pattern = format!("%{}%", like_pattern(text));
+                builder
+                    .push("(card.front LIKE ")
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\' OR card.back LIKE ")
+                    .push_bind(pattern)
+                    .push(" ESCAPE '\\')");
+            }
+            Search::Front(front) => {
+                builder
+                    .push("card.front LIKE ")
+                    .push_bind(like_pattern(front))
+                    .push(" ESCAPE '\\'");
+            }
+            Search::Back(back) => {
+                builder
+                    .push("card.back LIKE ")
+                    .push_bind(like_pattern(back))
+                    .push(" ESCAPE '\\'");
+            }
+            Search::Deck(deck) => {
+                let pattern = like_pattern(deck);
+                builder
+                    .push(
+                        "card.id IN (SELECT card_deck.card_id FROM card_deck \
+                        JOIN deck ON deck.id = card_deck.deck_id \
+                        WHERE (deck.name LIKE ",
+                    )
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\' OR deck.name LIKE ")
+                    .push_bind(format!("{}::%", pattern))
+                    .push(" ESCAPE '\\') AND (deck.owner_id IS NULL OR deck.owner_id = ")
+                    .push_bind(user_id)
+                    .push(") AND deck.deleted_at IS NULL)");
+            }
+            Search::Tag(tag) => {
+                let pattern = like_pattern(tag);
+                builder
+                    .push(
+                        "card.id IN (SELECT card_tag.card_id FROM card_tag \
+                        JOIN tag ON tag.id = card_tag.tag_id WHERE tag.name LIKE ",
+                    )
+                    .push_bind(pattern.clone())
+                    .push(" ESCAPE '\\' OR tag.name LIKE ")
+                    .push_bind(format!("{}::%", pattern))
+                    .push(" ESCAPE '\\')");
+            }
+            Search::Is(CardState::New) => {
+                builder
+                    .push("card.id NOT IN (SELECT card_id FROM card_schedule WHERE user_id IS ")
+                    .push_bind(user_id)
+                    .push(")");
+            }
+            Search::Is(CardState::Due) => {
+                builder
+                    .push(
+                        "card.id IN (SELECT card_id FROM card_schedule \
+                        WHERE due_at <= CURRENT_TIMESTAMP AND user_id IS ",
+                    )
+                    .push_bind(user_id)
+                    .push(")");
+            }
+            Search::Is(CardState::Suspended) => {
+                builder.push("card.suspended");
+            }
+            Search::Added(days) => {
+                builder
+                    .push("card.created_at >= datetime('now', ")
+                    .push_bind(format!("-{} days", days))
+                    .push(")");
+            }
+            Search::Rated(days, grade) => {
+                builder
+                    .push("card.id IN (SELECT card_id FROM answer WHERE user_id IS ")
+                    .push_bind(user_id)
+                    .push(" AND time >= datetime('now', ")
+                    .push_bind(format!("-{} days", days))
+                    .push(")");
+                if let Some(grade) = grade {
+                    builder.push(" AND grade = ").push_bind(*grade);
+                }
+                builder.push(")");
+            }
+            Search::Property(property, comparison, value) => {
+                builder
+                    .push("card.id IN (SELECT card_id FROM card_schedule WHERE user_id IS ")
+                    .push_bind(user_id)
+                    .push(format!(
+                        " AND {} {} ",
+                        property.column(),
+                        comparison.operator()
+                    ))
+                    .push_bind(*value)
+                    .push(")");
+            }
+            Search::Not(search) => {
+                builder.push("NOT (");
+                search.push_condition(builder, user_id);
+                builder.push(")");
+            }
+            Search::And(searches) | Search::Or(searches) if searches.is_empty() => {
+                builder.push("TRUE");
+            }
+            Search::And(searches) | Search::Or(searches) => {
+                let separator = match self {
+                    Search::And(_) => " AND ",
+                    _ => " OR ",
+                };
+                builder.push("(");
+                for (index, search) in searches.iter().enumerate() {
+                    if index > 0 {
+                        builder.push(separator);
+                    }
+                    search.push_condition(builder, user_id);
+                }
+                builder.push(")");
+            }
+        }
+    }
+
+    /// The full-text query that ranks the cards by how well they match the text of the search,
+    /// if it looks for any.
+    pub fn full_text_query(&self) -> Option<String> {
+        let mut phrases = vec![];
+        self.collect_phrases(&mut phrases);
+        if phrases.is_empty() {
+            return None;
+        }
+        Some(phrases.join(" OR "))
+    }
+
+    fn collect_phrases(&self, phrases: &mut Vec<String>) {
+        match self {
+            Search::Text(text) => phrases.extend(full_text_phrase(text)),
+            Search::And(searches) | Search::Or(searches) => {
+                for search in searches {
+                    search.collect_phrases(phrases);
+                }
+            }
+            // text that must not be there has nothing to rank by
+            _ => {}
+        }
+    }
+}
+
+/// Quotes text as an FTS5 phrase, which is a prefix when the text ends with `*`, or returns
+/// `None` when the index cannot look for it.
+fn full_text_phrase(text: &str) -> Option<String> {
+    let (words, prefix) = match text.strip_suffix('*') {
+        Some(words) => (words, "*"),
+        None => (text, ""),
+    };
+    if words.contains('*') || !words.chars().any(char::is_alphanumeric) {
+        return None;
+    }
+    Some(format!("\"{}\"{}", words.replace('"', "\"\""), prefix))
+}
+
+/// Turns a value with `*` wildcards into a LIKE pattern that escapes with a backslash.
+fn like_pattern(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+        .replace('*', "%")
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Not,
+    Or,
+    /// a term, along with whether it started with a quote and so is only text
+    Term(String, bool),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let quoted = c == '"';
+                let mut term = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    if c != '"' {
+                        term.push(c);
+                        continue;
+                    }
+                    // a quoted part runs to the next quote, spaces and parentheses included
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => term.push(c),
+                            None => return Err("a quote is never closed".to_string()),
+                        }
+                    }
+                }
+                if !quoted && term.eq_ignore_ascii_case("or") {
+                    tokens.push(Token::Or);
+                } else if quoted || !term.eq_ignore_ascii_case("and") {
+                    tokens.push(Token::Term(term, quoted));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn or(&mut self) -> Result<Search, String> {
+        let mut searches = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            searches.push(self.and()?);
+        }
+
+        Ok(match searches.len() {
+            1 => searches.remove(0),
+            _ => Search::Or(searches),
+        })
+    }
+
+    fn and(&mut self) -> Result<Search, String> {
+        let mut searches = vec![];
+        while !matches!(self.peek(), None | Some(Token::Or) | Some(Token::Close)) {
+            searches.push(self.unary()?);
+        }
+
+        Ok(match searches.len() {
+            0 => return Err("expected a search term".to_string()),
+            1 => searches.remove(0),
+            _ => Search::And(searches),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Search, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Search::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let search = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(search),
+                    _ => Err("a parenthesis is never closed".to_string()),
+                }
+            }
+            Some(Token::Term(term, true)) => Ok(Search::Text(term.clone())),
+            Some(Token::Term(term, false)) => parse_term(term),
+            _ => Err("expected a search term".to_string()),
+        }
+    }
+}
+
+fn parse_term(term: &str) -> Result<Search, String> {
+    let Some((field, value)) = term.split_once(':') else {
+        return Ok(Search::Text(term.to_string()));
+    };
+    let field = field.to_ascii_lowercase();
+    if ![
+        "front", "back", "deck", "tag", "is", "added", "rated", "prop",
+    ]
+    .contains(&field.as_str())
+    {
+        return Ok(Search::Text(term.to_string()));
+    }
+    if value.is_empty() {
+        return Err(format!("{} needs a value", field));
+    }
+
+    match field.as_str() {
+        "front" => Ok(Search::Front(value.to_string())),
+        "back" => Ok(Search::Back(value.to_string())),
+        "deck" => Ok(Search::Deck(value.to_string())),
+        "tag" => Ok(Search::Tag(value.to_string())),
+        "is" => match value.to_ascii_lowercase().as_str() {
+            "new" => Ok(Search::Is(CardState::New)),
+            "due" => Ok(Search::Is(CardState::Due)),
+            "suspended" => Ok(Search::Is(CardState::Suspended)),
+            _ => Err(format!("unknown card state \"{}\"", value)),
+        },
+        "added" => value
+            .parse()
+            .map(Search::Added)
+            .map_err(|_| format!("added takes a number of days, not \"{}\"", value)),
+        "rated" => parse_rated(value),
+        _ => parse_property(value),
+    }
+}
+
+fn parse_rated(value: &str) -> Result<Search, String> {
+    let (days, grade) = match value.split_once(':') {
+        Some((days, grade)) => (days, Some(grade)),
+        None => (value, None),
+    };
+    let days = days
+        .parse()
+        .map_err(|_| format!("rated takes a number of days, not \"{}\"", days))?;
+    let grade = match grade.map(str::parse) {
+        Some(Ok(grade @ 1..=4)) => Some(grade),
+        Some(_) => {
+            return Err(format!(
+                "expected a grade from 1 to 4 in \"rated:{}\"",
+                value
+            ))
+        }
+        None => None,
+    };
+
+    Ok(Search::Rated(days, grade))
+}
+
+fn parse_property(value: &str) -> Result<Search, String> {
+    let Some(start) = value.find(['<', '>', '=', '!']) else {
+        return Err(format!("expected a comparison in \"prop:{}\"", value));
+    };
+    let (name, rest) = value.split_at(start);
+    let property = match name.to_ascii_lowercase().as_str() {
+        "ease" => Property::Ease,
+        "ivl" => Property::Interval,
+        "reps" => Property::Repetitions,
+        "due" => Property::Due,
+        "stability" => Property::Stability,
+        "difficulty" => Property::Difficulty,
+        _ => return Err(format!("unknown card property \"{}\"", name)),
+    };
+    let (comparison, number) = [
+        ("<=", Comparison::LessOrEqual),
+        (">=", Comparison::GreaterOrEqual),
+        ("!=", Comparison::NotEqual),
+        ("<", Comparison::Less),
+        (">", Comparison::Greater),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find_map(|(operator, comparison)| Some((comparison, rest.strip_prefix(operator)?)))
+    .ok_or_else(|| format!("expected a comparison in \"prop:{}\"", value))?;
+    let number = number
+        .parse()
+        .map_err(|_| format!("expected a number in \"prop:{}\"", value))?;
+
+    Ok(Search::Property(property, comparison, number))
+}
+
+impl FromStr for Search {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(Search::And(vec![]));
+        }
+
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+        };
+        let search = parser.or()?;
+        match parser.peek() {
+            None => Ok(search),
+            Some(_) => Err("a parenthesis is closed without being opened".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sql(search: &str) -> String {
+        let mut builder = QueryBuilder::new("");
+        search
+            .parse::<Search>()
+            .unwrap()
+            .push_condition(&mut builder, None);
+        builder.into_sql()
+    }
+
+    #[test]
+    fn test_parse_terms() {
+        assert_eq!(
+            "deck:Spanish tag:verbs is:due front:*ar -is:suspended added:7 prop:ease<2.0"
+                .parse::<Search>(),
+            Ok(Search::And(vec![
+                Search::Deck("Spanish".to_string()),
+                Search::Tag("verbs".to_string()),
+                Search::Is(CardState::Due),
+                Search::Front("*ar".to_string()),
+                Search::Not(Box::new(Search::Is(CardState::Suspended))),
+                Search::Added(7),
+                Search::Property(Property::Ease, Comparison::Less, 2.0),
+            ]))
+        );
+        assert_eq!(
+            "rated:3:1 rated:7".parse::<Search>(),
+            Ok(Search::And(vec![
+                Search::Rated(3, Some(1)),
+                Search::Rated(7, None),
+            ]))
+        );
+        assert_eq!(
+            r#"hola "deck:x" back:"to be" 12:30"#.parse::<Search>(),
+            Ok(Search::And(vec![
+                Search::Text("hola".to_string()),
+                Search::Text("deck:x".to_string()),
+                Search::Back("to be".to_string()),
+                Search::Text("12:30".to_string()),
+            ]))
+        );
+        assert_eq!("".parse::<Search>(), Ok(Search::And(vec![])));
+    }
+
+    #[test]
+    fn test_parse_grouping() {
+        assert_eq!(
+            "a b or -(c OR d)".parse::<Search>(),
+            Ok(Search::Or(vec![
+                Search::And(vec![
+                    Search::Text("a".to_string()),
+                    Search::Text("b".to_string()),
+                ]),
+                Search::Not(Box::new(Search::Or(vec![
+                    Search::Text("c".to_string()),
+                    Search::Text("d".to_string()),
+                ]))),
+            ]))
+        );
+        assert_eq!("a and b".parse::<Search>(), "a b".parse::<Search>());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for search in [
+            "(a",
+            "a)",
+            "a or",
+            "-",
+            "\"a",
+            "is:",
+            "is:old",
+            "added:week",
+            "rated:3:5",
+            "rated:3:again",
+            "prop:ease",
+            "prop:age>1",
+            "prop:ease<much",
+        ] {
+            assert!(search.parse::<Search>().is_err(), "{}", search);
+        }
+    }
+
+    #[test]
+    fn test_push_condition() {
+        assert_eq!(sql(""), "TRUE");
+        assert_eq!(
+            sql("-a or front:b"),
+            "(NOT (card.id IN (SELECT rowid FROM card_fts WHERE card_fts MATCH ?)) \
+             OR card.front LIKE ? ESCAPE '\\')"
+        );
+        assert_eq!(
+            sql("*ar"),
+            "(card.front LIKE ? ESCAPE '\\' OR card.back LIKE ? ESCAPE '\\')"
+        );
+        assert_eq!(
+            sql("prop:ivl>=3"),
+            "card.id IN (SELECT card_id FROM card_schedule WHERE user_id IS ? \
+             AND interval_days >= ?)"
+        );
+    }
+
+    #[test]
+    fn test_full_text_query() {
+        assert_eq!(full_text_phrase("hola"), Some("\"hola\"".to_string()));
+        assert_eq!(full_text_phrase("habl*"), Some("\"habl\"*".to_string()));
+        assert_eq!(
+            full_text_phrase("say \"hi\""),
+            Some("\"say \"\"hi\"\"\"".to_string())
+        );
+        assert_eq!(full_text_phrase("*ar"), None);
+        assert_eq!(full_text_phrase("?!"), None);
+
+        let search: Search = "hola -adiós (deck:x or \"to be\") *ar".parse().unwrap();
+        assert_eq!(
+            search.full_text_query(),
+            Some("\"hola\" OR \"to be\"".to_string())
+        );
+        assert_eq!("is:due".parse::<Search>().unwrap().full_text_query(), None);
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!(like_pattern("*ar"), "%ar");
+        assert_eq!(like_pattern("100%_\\"), "100\\%\\_\\\\");
+    }
+}